@@ -1,4 +1,4 @@
-use glam::Vec3A;
+use glam::{Affine3A, Vec3A};
 
 use crate::{axis::Axis, ray::Ray};
 
@@ -130,6 +130,42 @@ impl AABB {
         );
     }
 
+    /// Box of the space inside both boxes. Disjoint boxes give a box without volume.
+    pub fn overlap(&self, other: &AABB) -> AABB {
+        let minimum = self.minimum.max(other.minimum);
+        let maximum = self.maximum.min(other.maximum);
+
+        AABB::new(minimum, maximum.max(minimum))
+    }
+
+    /// Returns the box enclosing all eight transformed corners.
+    pub fn transform(&self, transform: &Affine3A) -> AABB {
+        let mut aabb = AABB::empty();
+
+        for i in 0..8 {
+            let corner = Vec3A::new(
+                if i & 1 == 0 {
+                    self.minimum.x
+                } else {
+                    self.maximum.x
+                },
+                if i & 2 == 0 {
+                    self.minimum.y
+                } else {
+                    self.maximum.y
+                },
+                if i & 4 == 0 {
+                    self.minimum.z
+                } else {
+                    self.maximum.z
+                },
+            );
+            aabb.grow_mut(&transform.transform_point3a(corner));
+        }
+
+        aabb
+    }
+
     pub fn surface_area(&self) -> f32 {
         2.0 * (self.size().x * self.size().y
             + self.size().x * self.size().z
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use glam::{Affine3A, Quat, Vec3, Vec3A};
+
+use crate::{
+    camera::Camera,
+    hittable::Hittable,
+    instance::{AnimatedInstance, Instance, TransformKeyframe},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Interpolation {
+    Linear,
+    /// Catmull-Rom spline through the keyframes.
+    Cubic,
+}
+
+pub trait Interpolate: Copy {
+    fn lerp(a: Self, b: Self, s: f32) -> Self;
+
+    /// Catmull-Rom interpolation between `p1` and `p2`.
+    fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, s: f32) -> Self;
+}
+
+macro_rules! impl_interpolate {
+    ($t:ty) => {
+        impl Interpolate for $t {
+            fn lerp(a: Self, b: Self, s: f32) -> Self {
+                a + (b - a) * s
+            }
+
+            fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, s: f32) -> Self {
+                let s2 = s * s;
+                let s3 = s2 * s;
+
+                ((p1 * 2.0)
+                    + (p2 - p0) * s
+                    + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * s2
+                    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * s3)
+                    * 0.5
+            }
+        }
+    };
+}
+
+impl_interpolate!(f32);
+impl_interpolate!(Vec3);
+impl_interpolate!(Vec3A);
+
+/// Rotations always take the shortest arc between two keyframes, also in cubic tracks.
+impl Interpolate for Quat {
+    fn lerp(a: Self, b: Self, s: f32) -> Self {
+        a.slerp(b, s)
+    }
+
+    fn cubic(_p0: Self, p1: Self, p2: Self, _p3: Self, s: f32) -> Self {
+        p1.slerp(p2, s)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+}
+
+/// Value animated over time by interpolating keyframes. Before the first and after the last
+/// keyframe the track holds its value.
+#[derive(Clone, Debug)]
+pub struct Track<T: Interpolate> {
+    keyframes: Vec<Keyframe<T>>,
+    interpolation: Interpolation,
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new(mut keyframes: Vec<Keyframe<T>>, interpolation: Interpolation) -> Self {
+        assert!(!keyframes.is_empty(), "track without keyframes");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Self {
+            keyframes,
+            interpolation,
+        }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![Keyframe { time: 0.0, value }], Interpolation::Linear)
+    }
+
+    pub fn sample(&self, time: f32) -> T {
+        let keyframes = &self.keyframes;
+        let next = keyframes.partition_point(|keyframe| keyframe.time <= time);
+
+        if next == 0 {
+            return keyframes[0].value;
+        }
+        if next == keyframes.len() {
+            return keyframes[next - 1].value;
+        }
+
+        let (k1, k2) = (&keyframes[next - 1], &keyframes[next]);
+        let s = (time - k1.time) / (k2.time - k1.time);
+
+        match self.interpolation {
+            Interpolation::Linear => T::lerp(k1.value, k2.value, s),
+            Interpolation::Cubic => {
+                // Repeat the end keyframes as outer control points, so the curve still passes
+                // through the first and last keyframe
+                let p0 = if next >= 2 { &keyframes[next - 2] } else { k1 };
+                let p3 = keyframes.get(next + 1).unwrap_or(k2);
+                T::cubic(p0.value, k1.value, k2.value, p3.value, s)
+            }
+        }
+    }
+}
+
+/// Animated parameters of `Camera::new`.
+pub struct CameraAnimation {
+    pub lookfrom: Track<Vec3A>,
+    pub lookat: Track<Vec3A>,
+    pub vup: Vec3A,
+    pub vfov: Track<f32>,
+    pub aspect_ratio: f32,
+    pub aperture: Track<f32>,
+    pub focus_dist: Track<f32>,
+}
+
+impl CameraAnimation {
+    pub fn camera_at(&self, time: f32) -> Camera {
+        Camera::new(
+            self.lookfrom.sample(time),
+            self.lookat.sample(time),
+            self.vup,
+            self.vfov.sample(time),
+            self.aspect_ratio,
+            self.aperture.sample(time),
+            self.focus_dist.sample(time),
+        )
+    }
+}
+
+/// Shared object moved by animated scale, rotation and translation tracks.
+pub struct AnimatedObject {
+    pub object: Arc<dyn Hittable>,
+    pub scale: Track<Vec3>,
+    pub rotation: Track<Quat>,
+    pub translation: Track<Vec3>,
+}
+
+impl AnimatedObject {
+    pub fn transform_at(&self, time: f32) -> Affine3A {
+        Affine3A::from_scale_rotation_translation(
+            self.scale.sample(time),
+            self.rotation.sample(time),
+            self.translation.sample(time),
+        )
+    }
+
+    /// Places the object for a frame, moving it over the shutter interval for motion blur.
+    /// Objects holding still while the shutter is open are placed without interpolation.
+    pub fn instance(&self, time0: f32, time1: f32) -> Box<dyn Hittable> {
+        let (transform0, transform1) = (self.transform_at(time0), self.transform_at(time1));
+        if transform0 == transform1 {
+            return Box::new(Instance::new(self.object.clone(), transform0));
+        }
+
+        Box::new(AnimatedInstance::new(
+            self.object.clone(),
+            vec![
+                TransformKeyframe::new(time0, transform0),
+                TransformKeyframe::new(time1, transform1),
+            ],
+        ))
+    }
+}
+
+/// Keyframed camera and objects moving through a static scene, rendered as numbered frames.
+pub struct Animation {
+    pub camera: CameraAnimation,
+    /// The unchanging part of the scene, its BVH is built once and shared by all frames.
+    pub background: Arc<dyn Hittable>,
+    /// Unbounded objects of the unchanging part of the scene, see `Scene::unbounded`.
+    pub unbounded: Vec<Arc<dyn Hittable>>,
+    pub objects: Vec<AnimatedObject>,
+    pub frames_per_second: f32,
+    /// Fraction of the frame duration the shutter is open.
+    pub shutter: f32,
+}
+
+impl Animation {
+    pub fn frame_time(&self, frame: u32) -> f32 {
+        frame as f32 / self.frames_per_second
+    }
+
+    /// Returns the camera and the objects of a frame. Times of the camera rays are spread
+    /// over the open shutter, during which the animated objects move.
+    pub fn frame(&self, frame: u32) -> (Camera, Vec<Box<dyn Hittable>>) {
+        let time0 = self.frame_time(frame);
+        let time1 = time0 + self.shutter / self.frames_per_second;
+
+        let camera = self.camera.camera_at(time0).with_shutter(time0, time1);
+
+        let mut objects: Vec<Box<dyn Hittable>> = vec![Box::new(self.background.clone())];
+        for object in &self.objects {
+            objects.push(object.instance(time0, time1));
+        }
+
+        (camera, objects)
+    }
+}
@@ -9,6 +9,25 @@ pub enum Axis {
     Z = 2,
 }
 
+impl Axis {
+    /// The two other axes, in the order spanning a right-handed plane around `self`.
+    pub fn others(self) -> (Axis, Axis) {
+        match self {
+            Axis::X => (Axis::Y, Axis::Z),
+            Axis::Y => (Axis::Z, Axis::X),
+            Axis::Z => (Axis::X, Axis::Y),
+        }
+    }
+
+    pub fn unit(self) -> Vec3A {
+        match self {
+            Axis::X => Vec3A::X,
+            Axis::Y => Vec3A::Y,
+            Axis::Z => Vec3A::Z,
+        }
+    }
+}
+
 /// Make slices indexable by `Axis`.
 impl Index<Axis> for Vec3A {
     type Output = f32;
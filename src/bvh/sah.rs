@@ -5,7 +5,7 @@ use std::{cmp::Ordering, time::Instant};
 use crate::{
     aabb::AABB,
     hittable::Hittable,
-    ray::Ray,
+    ray::{HitRecord, Ray},
     util::{concatenate_vectors, joint_aabb},
 };
 
@@ -56,7 +56,7 @@ pub enum BVHNode {
 
 impl BVHNode {
     pub fn build(
-        shapes: &Vec<Box<dyn Hittable>>,
+        aabbs: &[AABB],
         indices: &[usize],
         nodes: &mut Vec<BVHNode>,
         parent_index: usize,
@@ -90,8 +90,7 @@ impl BVHNode {
         let mut centroid_bounds = AABB::empty();
 
         for index in indices {
-            (aabb_bounds, centroid_bounds) =
-                sum_aabbs(aabb_bounds, centroid_bounds, &shapes[*index].bounding_box())
+            (aabb_bounds, centroid_bounds) = sum_aabbs(aabb_bounds, centroid_bounds, &aabbs[*index])
         }
 
         let node_index = nodes.len();
@@ -109,14 +108,14 @@ impl BVHNode {
             < f32::EPSILON
         {
             let (child_l_indices, child_r_indices) = indices.split_at(indices.len() / 2);
-            let child_l_aabb = joint_aabb(child_l_indices, shapes);
-            let child_r_aabb = joint_aabb(child_r_indices, shapes);
+            let child_l_aabb = joint_aabb(child_l_indices, aabbs);
+            let child_r_aabb = joint_aabb(child_r_indices, aabbs);
 
             // Proceed recursively.
             let child_l_index =
-                BVHNode::build(shapes, child_l_indices, nodes, node_index, depth + 1);
+                BVHNode::build(aabbs, child_l_indices, nodes, node_index, depth + 1);
             let child_r_index =
-                BVHNode::build(shapes, child_r_indices, nodes, node_index, depth + 1);
+                BVHNode::build(aabbs, child_r_indices, nodes, node_index, depth + 1);
             (child_l_index, child_l_aabb, child_r_index, child_r_aabb)
         } else {
             const NUM_BUCKETS: usize = 6;
@@ -125,8 +124,7 @@ impl BVHNode {
             let mut bucket_assignments: [Vec<usize>; NUM_BUCKETS] = Default::default();
 
             for idx in indices {
-                let shape = &shapes[*idx];
-                let shape_aabb = shape.bounding_box();
+                let shape_aabb = aabbs[*idx];
                 let shape_center = shape_aabb.center();
 
                 let relative_pos = (shape_center[split_axis] - centroid_bounds.minimum[split_axis])
@@ -166,9 +164,9 @@ impl BVHNode {
             let child_r_indices = concatenate_vectors(r_assignments);
 
             let child_l_index =
-                BVHNode::build(shapes, &child_l_indices, nodes, node_index, depth + 1);
+                BVHNode::build(aabbs, &child_l_indices, nodes, node_index, depth + 1);
             let child_r_index =
-                BVHNode::build(shapes, &child_r_indices, nodes, node_index, depth + 1);
+                BVHNode::build(aabbs, &child_r_indices, nodes, node_index, depth + 1);
             (child_l_index, child_l_aabb, child_r_index, child_r_aabb)
         };
 
@@ -213,30 +211,53 @@ pub struct BVH {
 
 impl<'a> BVH {
     pub fn build(shapes: &Vec<Box<dyn Hittable>>) -> Self {
+        let aabbs = shapes
+            .iter()
+            .map(|shape| shape.bounding_box())
+            .collect::<Vec<AABB>>();
+
+        BVH::build_from_aabbs(&aabbs)
+    }
+
+    /// Builds the hierarchy over primitives that are only known by index, e.g. the triangles
+    /// of a `TriangleMesh`. Leaves refer to positions in `aabbs`. Without primitives the tree
+    /// is empty and no ray hits anything in it.
+    pub fn build_from_aabbs(aabbs: &[AABB]) -> Self {
+        if aabbs.is_empty() {
+            return BVH { nodes: vec![] };
+        }
+
         let now = Instant::now();
 
-        let mut indices = (0..shapes.len()).collect::<Vec<usize>>();
+        let mut indices = (0..aabbs.len()).collect::<Vec<usize>>();
 
-        let mut nodes = Vec::with_capacity(shapes.len() * 2);
+        let mut nodes = Vec::with_capacity(aabbs.len() * 2);
 
-        //BVHNode::build(shapes, &mut indices, &mut nodes, 0, 0);
-        BVHNode::build(shapes, &indices, &mut nodes, 0, 0);
+        //BVHNode::build(aabbs, &mut indices, &mut nodes, 0, 0);
+        BVHNode::build(aabbs, &indices, &mut nodes, 0, 0);
 
         println!(
             "BVH built in {:?} using {} shapes",
             now.elapsed(),
-            shapes.len()
+            aabbs.len()
         );
 
         BVH { nodes }
     }
 
-    pub fn traverse(&'a self, r: &Ray, shapes: &'a [Box<dyn Hittable>]) -> Vec<&Box<dyn Hittable>> {
+    /// Returns the indices of all primitives whose leaves are hit by the ray.
+    pub fn traverse_indices(&self, r: &Ray) -> Vec<usize> {
         let mut indices = Vec::new();
 
-        BVHNode::traverse(r, &self.nodes, 0, &mut indices);
+        if !self.nodes.is_empty() {
+            BVHNode::traverse(r, &self.nodes, 0, &mut indices);
+        }
 
         indices
+    }
+
+    pub fn traverse(&'a self, r: &Ray, shapes: &'a [Box<dyn Hittable>]) -> Vec<&Box<dyn Hittable>> {
+        self.traverse_indices(r)
             .iter()
             .map(|index| &shapes[*index])
             .collect::<Vec<_>>()
@@ -283,7 +304,52 @@ impl<'a> BVH {
                 }
             }
         }
-        print_node(nodes, 0);
+        if !nodes.is_empty() {
+            print_node(nodes, 0);
+        }
         println!("total surface area: {}", self.total_surface_area());
     }
 }
+
+/// Shapes together with their BVH, placed in a scene as a single `Hittable`. Lets a static
+/// part of a scene keep its hierarchy while the rest of the scene is rebuilt around it.
+pub struct BVHAggregate {
+    pub shapes: Vec<Box<dyn Hittable>>,
+    bvh: BVH,
+    aabb: AABB,
+}
+
+impl BVHAggregate {
+    pub fn new(shapes: Vec<Box<dyn Hittable>>) -> Self {
+        let mut aabb = AABB::empty();
+        for shape in &shapes {
+            aabb.join_mut(&shape.bounding_box());
+        }
+
+        Self {
+            bvh: BVH::build(&shapes),
+            shapes,
+            aabb,
+        }
+    }
+}
+
+impl Hittable for BVHAggregate {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut closest_hit_distance = t_max;
+        let mut closest_hit = None;
+
+        for shape in self.bvh.traverse(r, &self.shapes) {
+            if let Some(rec) = shape.hit(r, t_min, closest_hit_distance) {
+                closest_hit_distance = rec.t;
+                closest_hit = Some(rec);
+            }
+        }
+
+        closest_hit
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
@@ -16,8 +16,9 @@ pub struct Camera {
     pub v: Vec3A,
     pub w: Vec3A,
     pub lens_radius: f32,
-    pub _time0: f32,
-    pub _time1: f32,
+    /// Shutter open and close times, rays are spread uniformly over this interval.
+    pub time0: f32,
+    pub time1: f32,
 }
 
 impl Camera {
@@ -57,11 +58,17 @@ impl Camera {
             u,
             v,
             lens_radius,
-            _time0: 0.0,
-            _time1: 1.0,
+            time0: 0.0,
+            time1: 1.0,
         }
     }
 
+    pub fn with_shutter(mut self, time0: f32, time1: f32) -> Camera {
+        self.time0 = time0;
+        self.time1 = time1;
+        self
+    }
+
     pub fn get_ray(&self, s: f32, t: f32) -> Ray {
         let rd = self.lens_radius * random_in_unit_disk();
         let offset = self.u * rd.x + self.v * rd.y;
@@ -73,7 +80,7 @@ impl Camera {
             direction: self.lower_left_corner + s * self.horizontal + t * self.vertical
                 - self.origin
                 - offset,
-            time: rng.gen(),
+            time: self.time0 + rng.gen::<f32>() * (self.time1 - self.time0),
         }
     }
 }
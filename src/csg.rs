@@ -0,0 +1,98 @@
+use crate::{
+    aabb::AABB,
+    hittable::Hittable,
+    ray::{HitRecord, Ray},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    /// The left object with the right one cut out of it.
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOp::Union => in_left || in_right,
+            CsgOp::Intersection => in_left && in_right,
+            CsgOp::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// Combination of two closed objects. The intersections with both objects are walked along
+/// the ray, keeping track of whether the ray is inside each of them, and only those where the
+/// ray enters or leaves the combined solid are kept. Nodes can be nested to build up shapes.
+pub struct Csg<L: Hittable, R: Hittable> {
+    pub op: CsgOp,
+    pub left: L,
+    pub right: R,
+}
+
+impl<L: Hittable, R: Hittable> Csg<L, R> {
+    pub fn new(op: CsgOp, left: L, right: R) -> Self {
+        Self { op, left, right }
+    }
+}
+
+impl<L: Hittable, R: Hittable> Hittable for Csg<L, R> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.hit_all(r, t_min, t_max).into_iter().next()
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let left = self.left.bounding_box();
+
+        match self.op {
+            CsgOp::Union => left.join(&self.right.bounding_box()),
+            CsgOp::Intersection => left.overlap(&self.right.bounding_box()),
+            CsgOp::Difference => left,
+        }
+    }
+
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        let left_hits = self.left.hit_all(r, t_min, t_max);
+        let right_hits = self.right.hit_all(r, t_min, t_max);
+
+        // Starting out inside an object shows as leaving it at its first hit
+        let mut in_left = left_hits.first().is_some_and(|rec| !rec.front_face);
+        let mut in_right = right_hits.first().is_some_and(|rec| !rec.front_face);
+        let mut inside = self.op.inside(in_left, in_right);
+
+        let mut left_hits = left_hits.into_iter().peekable();
+        let mut right_hits = right_hits.into_iter().peekable();
+        let mut hits = vec![];
+
+        loop {
+            let from_left = match (left_hits.peek(), right_hits.peek()) {
+                (Some(left), Some(right)) => left.t <= right.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+
+            let mut rec = if from_left {
+                let rec = left_hits.next().unwrap();
+                in_left = rec.front_face;
+                rec
+            } else {
+                let rec = right_hits.next().unwrap();
+                in_right = rec.front_face;
+                rec
+            };
+
+            // The normal already faces the ray, only whether the ray enters the combined solid
+            // changes, e.g. the inside of the cut out object becomes outside.
+            let now_inside = self.op.inside(in_left, in_right);
+            if now_inside != inside {
+                inside = now_inside;
+                rec.front_face = now_inside;
+                hits.push(rec);
+            }
+        }
+
+        hits
+    }
+}
@@ -0,0 +1,396 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    sync::Arc,
+};
+
+use glam::Vec3A;
+
+use crate::{
+    aabb::AABB,
+    hittable::Hittable,
+    material::Material,
+    ray::{HitRecord, Ray},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveType {
+    /// Flat strip, oriented by the curve normals if it has them and facing the ray otherwise.
+    Ribbon,
+    /// Tube around the curve. Where the ray passes within the width, it is intersected with the
+    /// tube of the curve straightened at that point, which gives the hit distance and normal.
+    Round,
+}
+
+/// Cubic Bézier curve with a width varying linearly along it, for hair, fur and grass.
+///
+/// Long curves get loose bounding boxes, `split` cuts them into segments that each bound
+/// tightly, which is what the scene BVH works best with.
+pub struct Curve {
+    pub control_points: [Vec3A; 4],
+    pub widths: [f32; 2],
+    /// Normals of a ribbon at both ends, interpolated along the curve.
+    pub normals: Option<[Vec3A; 2]>,
+    pub curve_type: CurveType,
+    /// Range of the texture coordinate `u` along the curve, narrower for split segments.
+    pub u_range: [f32; 2],
+    pub material: Arc<dyn Material>,
+}
+
+fn eval_bezier(cp: &[Vec3A; 4], u: f32) -> (Vec3A, Vec3A) {
+    let cp1 = [
+        cp[0].lerp(cp[1], u),
+        cp[1].lerp(cp[2], u),
+        cp[2].lerp(cp[3], u),
+    ];
+    let cp2 = [cp1[0].lerp(cp1[1], u), cp1[1].lerp(cp1[2], u)];
+
+    let derivative = if (cp2[1] - cp2[0]).length_squared() > 0.0 {
+        3.0 * (cp2[1] - cp2[0])
+    } else {
+        // Coinciding control points at the ends
+        cp[3] - cp[0]
+    };
+
+    (cp2[0].lerp(cp2[1], u), derivative)
+}
+
+/// Blossom of the curve, whose diagonal values `(u, u, u)` are the points on the curve.
+fn blossom_bezier(cp: &[Vec3A; 4], u0: f32, u1: f32, u2: f32) -> Vec3A {
+    let a = [
+        cp[0].lerp(cp[1], u0),
+        cp[1].lerp(cp[2], u0),
+        cp[2].lerp(cp[3], u0),
+    ];
+    let b = [a[0].lerp(a[1], u1), a[1].lerp(a[2], u1)];
+
+    b[0].lerp(b[1], u2)
+}
+
+/// Control points of the part of the curve from `u0` to `u1`.
+fn sub_curve(cp: &[Vec3A; 4], u0: f32, u1: f32) -> [Vec3A; 4] {
+    [
+        blossom_bezier(cp, u0, u0, u0),
+        blossom_bezier(cp, u0, u0, u1),
+        blossom_bezier(cp, u0, u1, u1),
+        blossom_bezier(cp, u1, u1, u1),
+    ]
+}
+
+fn slerp(a: Vec3A, b: Vec3A, t: f32) -> Vec3A {
+    let cos_theta = a.dot(b).clamp(-1.0, 1.0);
+    if cos_theta > 0.9995 {
+        return a.lerp(b, t).normalize();
+    }
+
+    let theta = cos_theta.acos() * t;
+    let perpendicular = (b - a * cos_theta).normalize();
+
+    a * theta.cos() + perpendicular * theta.sin()
+}
+
+impl Curve {
+    pub fn new(
+        control_points: [Vec3A; 4],
+        widths: [f32; 2],
+        curve_type: CurveType,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            control_points,
+            widths,
+            normals: None,
+            curve_type,
+            u_range: [0.0, 1.0],
+            material,
+        }
+    }
+
+    pub fn with_normals(mut self, n0: Vec3A, n1: Vec3A) -> Self {
+        self.normals = Some([n0.normalize(), n1.normalize()]);
+        self
+    }
+
+    /// Cuts the curve into `segments` curves of equal parameter length.
+    pub fn split(&self, segments: usize) -> Vec<Curve> {
+        (0..segments)
+            .map(|i| {
+                let u0 = i as f32 / segments as f32;
+                let u1 = (i + 1) as f32 / segments as f32;
+                let u_at = |u: f32| self.u_range[0] + (self.u_range[1] - self.u_range[0]) * u;
+
+                Curve {
+                    control_points: sub_curve(&self.control_points, u0, u1),
+                    widths: [self.width(u0), self.width(u1)],
+                    normals: self
+                        .normals
+                        .map(|[n0, n1]| [slerp(n0, n1, u0), slerp(n0, n1, u1)]),
+                    curve_type: self.curve_type,
+                    u_range: [u_at(u0), u_at(u1)],
+                    material: self.material.clone(),
+                }
+            })
+            .collect()
+    }
+
+    fn width(&self, u: f32) -> f32 {
+        self.widths[0] + (self.widths[1] - self.widths[0]) * u
+    }
+
+    /// Intersects the curve in the coordinate system of the ray, where the ray starts at the
+    /// origin and runs along z. The curve is subdivided `depth` times, after which the pieces
+    /// are flat enough to be treated as line segments. Returns the distance along the ray and
+    /// the curve parameters of the nearest hit.
+    #[allow(clippy::too_many_arguments)]
+    fn recursive_intersect(
+        &self,
+        cp: &[Vec3A; 4],
+        u0: f32,
+        u1: f32,
+        depth: u32,
+        z_min: f32,
+        z_max: f32,
+        ribbon_scale: &dyn Fn(f32) -> f32,
+    ) -> Option<(f32, f32, f32)> {
+        // Reject pieces whose bounds, widened by the curve, miss the ray
+        let half_width = 0.5 * self.width(u0).max(self.width(u1));
+        let minimum = cp[0].min(cp[1]).min(cp[2].min(cp[3])) - Vec3A::splat(half_width);
+        let maximum = cp[0].max(cp[1]).max(cp[2].max(cp[3])) + Vec3A::splat(half_width);
+        if minimum.x > 0.0
+            || maximum.x < 0.0
+            || minimum.y > 0.0
+            || maximum.y < 0.0
+            || minimum.z > z_max
+            || maximum.z < z_min
+        {
+            return None;
+        }
+
+        if depth > 0 {
+            let u_mid = 0.5 * (u0 + u1);
+            let first = sub_curve(cp, 0.0, 0.5);
+            let second = sub_curve(cp, 0.5, 1.0);
+
+            let first_hit =
+                self.recursive_intersect(&first, u0, u_mid, depth - 1, z_min, z_max, ribbon_scale);
+            let z_max = first_hit.map_or(z_max, |(z, _, _)| z);
+            let second_hit =
+                self.recursive_intersect(&second, u_mid, u1, depth - 1, z_min, z_max, ribbon_scale);
+
+            // Only hits closer than the first one are found in the second half
+            return second_hit.or(first_hit);
+        }
+
+        // The ray has to pass between the lines perpendicular to the ends of the segment
+        let edge = (cp[1].y - cp[0].y) * -cp[0].y + cp[0].x * (cp[0].x - cp[1].x);
+        if edge < 0.0 {
+            return None;
+        }
+        let edge = (cp[2].y - cp[3].y) * -cp[3].y + cp[3].x * (cp[3].x - cp[2].x);
+        if edge < 0.0 {
+            return None;
+        }
+
+        // Closest point to the ray on the segment approximating the curve
+        let segment = cp[3].truncate() - cp[0].truncate();
+        let denom = segment.length_squared();
+        if denom == 0.0 {
+            return None;
+        }
+        let w = (-cp[0].truncate().dot(segment) / denom).clamp(0.0, 1.0);
+        let u = u0 + (u1 - u0) * w;
+
+        let hit_width = self.width(u) * ribbon_scale(u);
+
+        let (pc, dpcdw) = eval_bezier(cp, w);
+        let distance_squared = pc.x * pc.x + pc.y * pc.y;
+        if distance_squared > hit_width * hit_width * 0.25 || pc.z < z_min || pc.z > z_max {
+            return None;
+        }
+
+        // Which side of the curve the ray passes, for v across the width
+        let distance = distance_squared.sqrt();
+        let edge = dpcdw.x * -pc.y + pc.x * dpcdw.y;
+        let v = if edge > 0.0 {
+            0.5 + distance / hit_width
+        } else {
+            0.5 - distance / hit_width
+        };
+
+        Some((pc.z, u, v))
+    }
+
+    /// Number of subdivisions after which the curve is flat to a fraction of its width.
+    fn max_depth(&self, cp: &[Vec3A; 4]) -> u32 {
+        let mut flatness: f32 = 0.0;
+        for i in 0..2 {
+            let second_difference = (cp[i] - 2.0 * cp[i + 1] + cp[i + 2]).abs();
+            flatness = flatness.max(second_difference.max_element());
+        }
+
+        let epsilon = self.widths[0].max(self.widths[1]) * 0.05;
+        let depth = (std::f32::consts::SQRT_2 * 6.0 * flatness / (8.0 * epsilon)).log2() * 0.5;
+
+        (depth.round().max(0.0) as u32).min(10)
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let length = r.direction.length();
+        let dz = r.direction / length;
+
+        // Line the x axis up with the curve so that its bounds in ray space are tight
+        let chord = self.control_points[3] - self.control_points[0];
+        let dx = match dz.cross(chord).try_normalize() {
+            Some(dx) => dx,
+            None => dz.any_orthonormal_vector(),
+        };
+        let dy = dz.cross(dx);
+
+        let to_ray_space = |p: Vec3A| {
+            let p = p - r.origin;
+            Vec3A::new(p.dot(dx), p.dot(dy), p.dot(dz))
+        };
+        let cp = self.control_points.map(to_ray_space);
+
+        // Ribbons seen at an angle get narrower
+        let ribbon_scale = |u: f32| match (self.curve_type, self.normals) {
+            (CurveType::Ribbon, Some([n0, n1])) => slerp(n0, n1, u).dot(dz).abs(),
+            _ => 1.0,
+        };
+
+        let (z, u, v) = self.recursive_intersect(
+            &cp,
+            0.0,
+            1.0,
+            self.max_depth(&cp),
+            t_min * length,
+            t_max * length,
+            &ribbon_scale,
+        )?;
+        let mut t = z / length;
+
+        let (point, tangent) = eval_bezier(&self.control_points, u);
+        let tangent = tangent.normalize();
+
+        // Direction towards the ray origin across the curve
+        let facing = (-dz + tangent * tangent.dot(dz))
+            .try_normalize()
+            .unwrap_or(-dz);
+
+        let outward_normal = match (self.curve_type, self.normals) {
+            (CurveType::Ribbon, Some([n0, n1])) => slerp(n0, n1, u),
+            (CurveType::Ribbon, None) => facing,
+            (CurveType::Round, _) => {
+                // Ray against the infinite cylinder along the tangent, in the plane across it
+                let radius = 0.5 * self.width(u);
+                let across = |v: Vec3A| v - tangent * tangent.dot(v);
+                let direction = across(r.direction);
+                let offset = across(r.origin - point);
+
+                let a = direction.length_squared();
+                if a > 0.0 {
+                    let half_b = offset.dot(direction);
+                    let c = offset.length_squared() - radius * radius;
+                    // The ray passes within the radius, up to the error of the subdivision
+                    let sqrtd = (half_b * half_b - a * c).max(0.0).sqrt();
+
+                    let near = (-half_b - sqrtd) / a;
+                    t = if near >= t_min {
+                        near
+                    } else {
+                        (-half_b + sqrtd) / a
+                    };
+                    if t < t_min || t > t_max {
+                        return None;
+                    }
+                }
+
+                across(r.at(t) - point).try_normalize().unwrap_or(facing)
+            }
+        };
+
+        Some(HitRecord::new(
+            r,
+            t,
+            outward_normal,
+            self.u_range[0] + (self.u_range[1] - self.u_range[0]) * u,
+            v,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let cp = &self.control_points;
+        let half_width = Vec3A::splat(0.5 * self.widths[0].max(self.widths[1]));
+
+        AABB::new(
+            cp[0].min(cp[1]).min(cp[2].min(cp[3])) - half_width,
+            cp[0].max(cp[1]).max(cp[2].max(cp[3])) + half_width,
+        )
+    }
+}
+
+/// Loads curves from a text file with one curve per line: the four control points, the widths
+/// at both ends and optionally the normals at both ends, for ribbons. Empty lines and lines
+/// starting with `#` are skipped. Every curve is split into `segments` for the BVH.
+///
+/// ```text
+/// # x0 y0 z0  x1 y1 z1  x2 y2 z2  x3 y3 z3  width0 width1  [nx0 ny0 nz0  nx1 ny1 nz1]
+/// 0 0 0  0 0.3 0  0.1 0.6 0  0.2 1 0  0.02 0.005
+/// ```
+pub fn load_curves(
+    path: &str,
+    curve_type: CurveType,
+    segments: usize,
+    material: Arc<dyn Material>,
+) -> Result<Vec<Curve>> {
+    let mut curves = vec![];
+
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let values = line
+            .split_whitespace()
+            .map(|value| value.parse::<f32>())
+            .collect::<std::result::Result<Vec<f32>, _>>()
+            .map_err(|err| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{}:{}: {}", path, number + 1, err),
+                )
+            })?;
+
+        if values.len() != 14 && values.len() != 20 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{}:{}: expected 14 or 20 values, found {}",
+                    path,
+                    number + 1,
+                    values.len()
+                ),
+            ));
+        }
+
+        let point = |i: usize| Vec3A::new(values[i], values[i + 1], values[i + 2]);
+
+        let mut curve = Curve::new(
+            [point(0), point(3), point(6), point(9)],
+            [values[12], values[13]],
+            curve_type,
+            material.clone(),
+        );
+        if values.len() == 20 {
+            curve = curve.with_normals(point(14), point(17));
+        }
+
+        curves.extend(curve.split(segments));
+    }
+
+    Ok(curves)
+}
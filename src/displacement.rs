@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use glam::{Vec2, Vec3A};
+
+use crate::{mesh::TriangleMesh, texture::Texture};
+
+/// Vertices of the mesh being tessellated, with the midpoints of split edges shared between
+/// the triangles on both sides of them.
+struct Tessellation {
+    positions: Vec<Vec3A>,
+    normals: Vec<Vec3A>,
+    uvs: Vec<Vec2>,
+    midpoints: HashMap<(u32, u32), u32>,
+    indices: Vec<[u32; 3]>,
+    max_edge_length: f32,
+}
+
+impl Tessellation {
+    fn midpoint(&mut self, a: u32, b: u32) -> u32 {
+        let key = (a.min(b), a.max(b));
+        if let Some(&index) = self.midpoints.get(&key) {
+            return index;
+        }
+
+        let (a, b) = (a as usize, b as usize);
+        // Symmetric in a and b, so midpoints of split copies of an edge land on the same bits
+        self.positions
+            .push((self.positions[a] + self.positions[b]) * 0.5);
+        self.normals
+            .push((self.normals[a] + self.normals[b]).normalize_or_zero());
+        self.uvs.push(self.uvs[a].lerp(self.uvs[b], 0.5));
+
+        let index = self.positions.len() as u32 - 1;
+        self.midpoints.insert(key, index);
+        index
+    }
+
+    fn too_long(&self, a: u32, b: u32) -> bool {
+        (self.positions[a as usize] - self.positions[b as usize]).length() > self.max_edge_length
+    }
+
+    /// Splits the edges of a triangle that are longer than the target length and recurses into
+    /// the pieces. Whether an edge is split only depends on the edge, so the triangles on both
+    /// sides of it agree and no cracks open up between them.
+    fn split(&mut self, [a, b, c]: [u32; 3]) {
+        let split = [
+            self.too_long(a, b),
+            self.too_long(b, c),
+            self.too_long(c, a),
+        ];
+
+        // Rotate the triangle so the split edges come first
+        let [a, b, c] = match split {
+            [false, false, false] => {
+                self.indices.push([a, b, c]);
+                return;
+            }
+            [true, true, true] => {
+                let (ab, bc, ca) = (
+                    self.midpoint(a, b),
+                    self.midpoint(b, c),
+                    self.midpoint(c, a),
+                );
+                for triangle in [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]] {
+                    self.split(triangle);
+                }
+                return;
+            }
+            [true, false, false] | [true, true, false] => [a, b, c],
+            [false, true, false] | [false, true, true] => [b, c, a],
+            [false, false, true] | [true, false, true] => [c, a, b],
+        };
+
+        let ab = self.midpoint(a, b);
+        if self.too_long(b, c) {
+            let bc = self.midpoint(b, c);
+            for triangle in [[a, ab, c], [ab, b, bc], [ab, bc, c]] {
+                self.split(triangle);
+            }
+        } else {
+            for triangle in [[a, ab, c], [ab, b, c]] {
+                self.split(triangle);
+            }
+        }
+    }
+}
+
+/// Tessellates a mesh until no edge is longer than `edge_length`, then moves every vertex
+/// along its normal by `scale` times the texture value at it, averaged over the color
+/// channels. Meshes without shading normals are displaced along the average of the face
+/// normals around each vertex. The displaced mesh gets new shading normals from its own faces.
+///
+/// Vertices split at uv seams or hard edges share one normal and one height per position, the
+/// heights averaged over the copies, so the copies move together and the surface stays closed.
+pub fn displace(
+    mesh: &TriangleMesh,
+    texture: &dyn Texture,
+    scale: f32,
+    edge_length: f32,
+) -> TriangleMesh {
+    assert!(
+        edge_length > 0.0,
+        "displacement needs a positive edge length"
+    );
+
+    let normals = if mesh.normals.is_empty() {
+        vertex_normals(&mesh.positions, &mesh.indices)
+    } else {
+        weld(&mesh.positions, &mesh.normals)
+    };
+    let uvs = if mesh.uvs.is_empty() {
+        vec![Vec2::ZERO; mesh.positions.len()]
+    } else {
+        mesh.uvs.clone()
+    };
+
+    let mut tessellation = Tessellation {
+        positions: mesh.positions.clone(),
+        normals,
+        uvs,
+        midpoints: HashMap::new(),
+        indices: vec![],
+        max_edge_length: edge_length,
+    };
+
+    for &triangle in &mesh.indices {
+        tessellation.split(triangle);
+    }
+
+    let Tessellation {
+        positions,
+        normals,
+        uvs,
+        indices,
+        ..
+    } = tessellation;
+
+    let heights = positions
+        .iter()
+        .zip(&uvs)
+        .map(|(&p, uv)| texture.value(uv.x, uv.y, p).dot(Vec3A::ONE) / 3.0)
+        .collect::<Vec<_>>();
+    let heights = weld_heights(&positions, &heights);
+
+    let positions = positions
+        .iter()
+        .zip(&normals)
+        .zip(heights)
+        .map(|((&p, &normal), height)| p + scale * height * normal)
+        .collect::<Vec<_>>();
+
+    let normals = vertex_normals(&positions, &indices);
+    let uvs = if mesh.uvs.is_empty() { vec![] } else { uvs };
+
+    TriangleMesh::new(positions, normals, uvs, indices, mesh.material.clone())
+}
+
+/// Normals at the vertices, averaged over the faces around them and weighted by their area.
+fn vertex_normals(positions: &[Vec3A], indices: &[[u32; 3]]) -> Vec<Vec3A> {
+    let mut normals = vec![Vec3A::ZERO; positions.len()];
+
+    for triangle in indices {
+        let [a, b, c] = triangle.map(|i| positions[i as usize]);
+        let normal = (b - a).cross(c - a);
+
+        for i in triangle {
+            normals[*i as usize] += normal;
+        }
+    }
+
+    weld(positions, &normals)
+}
+
+fn position_key(p: &Vec3A) -> [u32; 3] {
+    p.to_array().map(f32::to_bits)
+}
+
+/// Normals summed over all the vertices at the same position and normalized, so split copies
+/// of a vertex end up with the same one.
+fn weld(positions: &[Vec3A], normals: &[Vec3A]) -> Vec<Vec3A> {
+    let mut sums = HashMap::new();
+    for (p, &normal) in positions.iter().zip(normals) {
+        *sums.entry(position_key(p)).or_insert(Vec3A::ZERO) += normal;
+    }
+
+    positions
+        .iter()
+        .map(|p| sums[&position_key(p)].normalize_or_zero())
+        .collect()
+}
+
+/// Heights averaged over all the vertices at the same position, so split copies of a vertex
+/// with different texture coordinates end up with the same one.
+fn weld_heights(positions: &[Vec3A], heights: &[f32]) -> Vec<f32> {
+    let mut sums = HashMap::new();
+    for (p, &height) in positions.iter().zip(heights) {
+        let (sum, count) = sums.entry(position_key(p)).or_insert((0.0, 0));
+        *sum += height;
+        *count += 1;
+    }
+
+    positions
+        .iter()
+        .map(|p| {
+            let (sum, count) = sums[&position_key(p)];
+            sum / count as f32
+        })
+        .collect()
+}
@@ -1,12 +1,14 @@
 use std::{f32::consts::PI, sync::Arc};
 
-use glam::Vec3A;
+use glam::{Vec2, Vec3A};
 
 use crate::{
     aabb::AABB,
+    axis::Axis,
     hittable::Hittable,
     material::Material,
     ray::{HitRecord, Ray},
+    util::{solve_quadratic, solve_quartic},
 };
 
 pub struct Sphere {
@@ -24,50 +26,71 @@ impl Sphere {
     }
 }
 
-impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let oc = ray.origin - self.position;
+fn hit_sphere(
+    position: Vec3A,
+    radius: f32,
+    material: &Arc<dyn Material>,
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<HitRecord> {
+    let oc = ray.origin - position;
 
-        let a = ray.direction.length_squared();
-        let half_b = oc.dot(ray.direction);
-        let c = oc.length_squared() - self.radius * self.radius;
-        let discriminant = half_b * half_b - a * c;
+    let a = ray.direction.length_squared();
+    let half_b = oc.dot(ray.direction);
+    let c = oc.length_squared() - radius * radius;
+    let discriminant = half_b * half_b - a * c;
 
-        if discriminant < 0.0 {
-            return None;
-        }
+    if discriminant < 0.0 {
+        return None;
+    }
 
-        let sqrtd = discriminant.sqrt();
+    let sqrtd = discriminant.sqrt();
 
-        let mut root = (-half_b - sqrtd) / a;
+    let mut root = (-half_b - sqrtd) / a;
+    if root < t_min || root > t_max {
+        root = (-half_b + sqrtd) / a;
         if root < t_min || root > t_max {
-            root = (-half_b + sqrtd) / a;
-            if root < t_min || root > t_max {
-                return None;
-            }
+            return None;
         }
+    }
 
-        let outward_normal = (ray.at(root) - self.position) / self.radius;
-        let front_face = ray.direction.dot(outward_normal) < 0.0;
-        let mut normal = outward_normal;
-        if !front_face {
-            normal = -outward_normal;
-        }
+    let outward_normal = (ray.at(root) - position) / radius;
+    let front_face = ray.direction.dot(outward_normal) < 0.0;
+    let mut normal = outward_normal;
+    if !front_face {
+        normal = -outward_normal;
+    }
 
-        let uv_coords = Sphere::get_sphere_uv(outward_normal);
+    let uv_coords = Sphere::get_sphere_uv(outward_normal);
 
-        let closest_hit = HitRecord {
-            t: root,
-            p: ray.at(root),
-            normal,
-            u: uv_coords.0,
-            v: uv_coords.1,
-            front_face,
-            material: self.material.clone(),
-        };
+    let closest_hit = HitRecord {
+        t: root,
+        p: ray.at(root),
+        normal,
+        u: uv_coords.0,
+        v: uv_coords.1,
+        barycentric: Vec2::ZERO,
+        front_face,
+        material: material.clone(),
+        media: None,
+    };
+
+    Some(closest_hit)
+}
 
-        Some(closest_hit)
+impl Hittable for Sphere {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_sphere(
+            self.position,
+            self.radius,
+            &self.material,
+            ray,
+            t_min,
+            t_max,
+        )
     }
+
     fn bounding_box(&self) -> AABB {
         AABB {
             minimum: self.position - Vec3A::new(self.radius, self.radius, self.radius),
@@ -76,64 +99,870 @@ impl Hittable for Sphere {
     }
 }
 
+/// Sphere moving linearly from `center0` at `time0` to `center1` at `time1`, resting at the
+/// nearest end outside of that interval.
+pub struct MovingSphere {
+    pub center0: Vec3A,
+    pub center1: Vec3A,
+    pub time0: f32,
+    pub time1: f32,
+    pub radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center0: Vec3A,
+        center1: Vec3A,
+        time0: f32,
+        time1: f32,
+        radius: f32,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(time0 <= time1, "motion ends before it starts");
+
+        Self {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    /// Center at `time`, held at the ends outside of the motion. Spheres moving over an empty
+    /// interval stay at `center0`.
+    pub fn center(&self, time: f32) -> Vec3A {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+
+        let time = time.clamp(self.time0, self.time1);
+        self.center0
+            + ((time - self.time0) / (self.time1 - self.time0)) * (self.center1 - self.center0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_sphere(
+            self.center(ray.time),
+            self.radius,
+            &self.material,
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let radius = Vec3A::new(self.radius, self.radius, self.radius);
+        let box0 = AABB::new(self.center0 - radius, self.center0 + radius);
+        let box1 = AABB::new(self.center1 - radius, self.center1 + radius);
+
+        box0.join(&box1)
+    }
+}
+
+/// Watertight ray/triangle intersection after Woop, Benthin and Wald (2013). The triangle is
+/// transformed into a space where the ray runs along +z, so edges shared between triangles are
+/// tested identically and rays can't slip through cracks. Returns the ray parameter and the
+/// barycentric coordinates of `vertex1` and `vertex2`.
+pub fn intersect_triangle(
+    r: &Ray,
+    vertex0: Vec3A,
+    vertex1: Vec3A,
+    vertex2: Vec3A,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, f32, f32)> {
+    let dir_abs = r.direction.abs();
+    let kz = if dir_abs.x > dir_abs.y && dir_abs.x > dir_abs.z {
+        0
+    } else if dir_abs.y > dir_abs.z {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    // Swap to keep the winding order of the triangle
+    if r.direction[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    let sx = r.direction[kx] / r.direction[kz];
+    let sy = r.direction[ky] / r.direction[kz];
+    let sz = 1.0 / r.direction[kz];
+
+    let a = vertex0 - r.origin;
+    let b = vertex1 - r.origin;
+    let c = vertex2 - r.origin;
+
+    let ax = a[kx] - sx * a[kz];
+    let ay = a[ky] - sy * a[kz];
+    let bx = b[kx] - sx * b[kz];
+    let by = b[ky] - sy * b[kz];
+    let cx = c[kx] - sx * c[kz];
+    let cy = c[ky] - sy * c[kz];
+
+    let mut u = cx * by - cy * bx;
+    let mut v = ax * cy - ay * cx;
+    let mut w = bx * ay - by * ax;
+
+    // Fall back to double precision when the ray passes exactly through an edge
+    if u == 0.0 || v == 0.0 || w == 0.0 {
+        u = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+        v = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+        w = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+    }
+
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        // not within bounds of triangle
+        return None;
+    }
+
+    let det = u + v + w;
+    if det == 0.0 {
+        // Parallel to the ray
+        return None;
+    }
+
+    let az = sz * a[kz];
+    let bz = sz * b[kz];
+    let cz = sz * c[kz];
+    let t_scaled = u * az + v * bz + w * cz;
+
+    // Compare against the interval before dividing, flipping the comparison for negative det
+    if det > 0.0 && (t_scaled < t_min * det || t_scaled > t_max * det)
+        || det < 0.0 && (t_scaled > t_min * det || t_scaled < t_max * det)
+    {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+
+    Some((t_scaled * inv_det, v * inv_det, w * inv_det))
+}
+
 #[derive(Debug)]
 pub struct Triangle {
     pub vertex0: Vec3A,
     pub vertex1: Vec3A,
     pub vertex2: Vec3A,
+    pub uv0: Vec2,
+    pub uv1: Vec2,
+    pub uv2: Vec2,
+    /// Skip hits on the side facing away from the counter-clockwise winding order.
+    pub cull_backfaces: bool,
     pub material: Arc<dyn Material>,
 }
 
+impl Triangle {
+    /// Creates a triangle without texture coordinates, the vertices get the corners of UV space
+    /// so that textures are sampled by the barycentric coordinates.
+    pub fn new(
+        vertex0: Vec3A,
+        vertex1: Vec3A,
+        vertex2: Vec3A,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            vertex0,
+            vertex1,
+            vertex2,
+            uv0: Vec2::new(0.0, 0.0),
+            uv1: Vec2::new(1.0, 0.0),
+            uv2: Vec2::new(0.0, 1.0),
+            cull_backfaces: false,
+            material,
+        }
+    }
+}
+
 impl Hittable for Triangle {
-    fn hit(&self, r: &Ray, _t_min: f32, _t_max: f32) -> Option<HitRecord> {
-        let edge1 = self.vertex1 - self.vertex0;
-        let edge2 = self.vertex2 - self.vertex0;
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let outward_normal = (self.vertex1 - self.vertex0)
+            .cross(self.vertex2 - self.vertex0)
+            .normalize();
 
-        let normal = edge1.cross(edge2);
+        let front_face = r.direction.dot(outward_normal) < 0.0;
+        if self.cull_backfaces && !front_face {
+            return None;
+        }
+
+        let (t, u, v) =
+            intersect_triangle(r, self.vertex0, self.vertex1, self.vertex2, t_min, t_max)?;
+
+        let mut rec = HitRecord::new(r, t, outward_normal, 0.0, 0.0, &self.material);
+        rec.barycentric = Vec2::new(u, v);
 
-        let pvec = r.direction.cross(edge2);
+        let uv = rec.interpolate([self.uv0, self.uv1, self.uv2]);
+        (rec.u, rec.v) = (uv.x, uv.y);
 
-        let det = edge1.dot(pvec);
-        if det < f32::EPSILON {
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let minimum = self.vertex0.min(self.vertex1.min(self.vertex2));
+        let maximum = self.vertex0.max(self.vertex1.max(self.vertex2));
+
+        AABB::new(minimum, maximum)
+    }
+}
+
+/// Infinite plane through `point`, facing along `normal`. Texture coordinates are the distances
+/// along two directions in the plane, so a texture repeats once per unit.
+///
+/// Its bounding box is infinite, which would ruin the BVH, so planes go in
+/// `Scene::unbounded`, as `Scene::set` does, and are tested for every ray.
+pub struct Plane {
+    point: Vec3A,
+    normal: Vec3A,
+    tangent: Vec3A,
+    bitangent: Vec3A,
+    pub material: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Vec3A, normal: Vec3A, material: Arc<dyn Material>) -> Self {
+        let normal = normal.normalize();
+        let (tangent, bitangent) = normal.any_orthonormal_pair();
+
+        Self {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let denom = self.normal.dot(r.direction);
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        // Relative to a point on the plane, as the origin may be far from it
+        let t = self.normal.dot(self.point - r.origin) / denom;
+        if !(t_min..=t_max).contains(&t) {
+            return None;
+        }
+
+        let planar = r.at(t) - self.point;
+        let u = self.tangent.dot(planar);
+        let v = self.bitangent.dot(planar);
+
+        Some(HitRecord::new(
+            r,
+            t,
+            self.normal,
+            u - u.floor(),
+            v - v.floor(),
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::new(Vec3A::splat(f32::NEG_INFINITY), Vec3A::splat(f32::INFINITY))
+    }
+}
+
+/// Parallelogram spanned by the edges `u` and `v` from the corner `q`. The texture coordinates
+/// run from 0 to 1 along both edges.
+pub struct Quad {
+    q: Vec3A,
+    u: Vec3A,
+    v: Vec3A,
+    normal: Vec3A,
+    d: f32,
+    w: Vec3A,
+    pub material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(q: Vec3A, u: Vec3A, v: Vec3A, material: Arc<dyn Material>) -> Self {
+        let n = u.cross(v);
+        let normal = n.normalize();
+
+        Self {
+            q,
+            u,
+            v,
+            normal,
+            d: normal.dot(q),
+            w: n / n.dot(n),
+            material,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let denom = self.normal.dot(r.direction);
+        if denom.abs() < 1e-8 {
             // Parallel to the ray
             return None;
         }
 
-        let inv_det = 1.0 / det;
+        let t = (self.d - self.normal.dot(r.origin)) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
 
-        let tvec = r.origin - self.vertex0;
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        Some(HitRecord::new(
+            r,
+            t,
+            self.normal,
+            alpha,
+            beta,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::empty()
+            .grow(&self.q)
+            .grow(&(self.q + self.u))
+            .grow(&(self.q + self.v))
+            .grow(&(self.q + self.u + self.v))
+    }
+}
 
-        let u = tvec.dot(pvec) * inv_det;
-        if !(0.0..=1.0).contains(&u) {
-            // not within bounds of triangle
+/// Rectangle at `k` on the `axis`, e.g. `Axis::Y` gives a rectangle in the XZ plane, spanning
+/// `a0..a1` and `b0..b1` on the other two axes as ordered by `Axis::others`. Its normal points
+/// along the positive axis.
+pub struct AxisRect {
+    pub axis: Axis,
+    pub a0: f32,
+    pub a1: f32,
+    pub b0: f32,
+    pub b1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Hittable for AxisRect {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (axis_a, axis_b) = self.axis.others();
+
+        let t = (self.k - r.origin[self.axis]) / r.direction[self.axis];
+        if !(t_min..=t_max).contains(&t) {
             return None;
         }
 
-        let qvec = tvec.cross(edge1);
+        let p = r.at(t);
+        let a = p[axis_a];
+        let b = p[axis_b];
+        if a < self.a0 || a > self.a1 || b < self.b0 || b > self.b1 {
+            return None;
+        }
+
+        Some(HitRecord::new(
+            r,
+            t,
+            self.axis.unit(),
+            (a - self.a0) / (self.a1 - self.a0),
+            (b - self.b0) / (self.b1 - self.b0),
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let (axis_a, axis_b) = self.axis.others();
+
+        let corner =
+            |a: f32, b: f32| axis_a.unit() * a + axis_b.unit() * b + self.axis.unit() * self.k;
+
+        AABB::new(corner(self.a0, self.b0), corner(self.a1, self.b1))
+    }
+}
+
+/// Axis-aligned box made of six rectangles, each with its own texture coordinates.
+pub struct Cuboid {
+    minimum: Vec3A,
+    maximum: Vec3A,
+    sides: Vec<AxisRect>,
+}
+
+impl Cuboid {
+    pub fn new(minimum: Vec3A, maximum: Vec3A, material: Arc<dyn Material>) -> Self {
+        let mut sides = Vec::with_capacity(6);
+
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            let (axis_a, axis_b) = axis.others();
+
+            for k in [minimum[axis], maximum[axis]] {
+                sides.push(AxisRect {
+                    axis,
+                    a0: minimum[axis_a],
+                    a1: maximum[axis_a],
+                    b0: minimum[axis_b],
+                    b1: maximum[axis_b],
+                    k,
+                    material: material.clone(),
+                });
+            }
+        }
+
+        Self {
+            minimum,
+            maximum,
+            sides,
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut closest_hit_distance = t_max;
+        let mut closest_hit = None;
+
+        for (index, side) in self.sides.iter().enumerate() {
+            if let Some(rec) = side.hit(r, t_min, closest_hit_distance) {
+                closest_hit_distance = rec.t;
+                closest_hit = Some((index, rec));
+            }
+        }
+
+        // The rectangles face along the positive axes, flip the sides at the minimum so that
+        // `front_face` tells whether the ray enters the box
+        closest_hit.map(|(index, mut rec)| {
+            if index % 2 == 0 {
+                rec.front_face = !rec.front_face;
+            }
+            rec
+        })
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::new(self.minimum, self.maximum)
+    }
+}
+
+/// Intersection with one of the surfaces of a shape in its local frame.
+struct SurfaceHit {
+    t: f32,
+    outward_normal: Vec3A,
+    u: f32,
+    v: f32,
+}
+
+impl SurfaceHit {
+    fn record(self, r: &Ray, material: &Arc<dyn Material>) -> HitRecord {
+        HitRecord::new(r, self.t, self.outward_normal, self.u, self.v, material)
+    }
+}
+
+/// Angle around the local y axis as a texture coordinate, running the same way as on spheres.
+fn azimuth(p: Vec3A) -> f32 {
+    ((-p.z).atan2(p.x) + PI) / (2.0 * PI)
+}
+
+/// Nearest of the roots of `a t^2 + b t + c` within `t_min..=t_max` for which `accept` holds.
+fn nearest_root(
+    a: f32,
+    b: f32,
+    c: f32,
+    t_min: f32,
+    t_max: f32,
+    accept: impl Fn(f32) -> bool,
+) -> Option<f32> {
+    solve_quadratic(a as f64, b as f64, c as f64)
+        .into_iter()
+        .map(|t| t as f32)
+        .filter(|t| (t_min..=t_max).contains(t) && accept(*t))
+        .min_by(f32::total_cmp)
+}
+
+/// Intersects the disk of radius `radius` at height `y` on the local y axis, given the ray
+/// origin relative to the center of the shape.
+fn hit_cap(
+    oc: Vec3A,
+    direction: Vec3A,
+    y: f32,
+    radius: f32,
+    outward_normal: Vec3A,
+    t_min: f32,
+    t_max: f32,
+) -> Option<SurfaceHit> {
+    let t = (y - oc.y) / direction.y;
+    if !(t_min..=t_max).contains(&t) {
+        return None;
+    }
+
+    let p = oc + t * direction;
+    let r = (p.x * p.x + p.z * p.z).sqrt();
+    if r > radius {
+        return None;
+    }
+
+    Some(SurfaceHit {
+        t,
+        outward_normal,
+        u: azimuth(p),
+        v: r / radius,
+    })
+}
+
+fn nearest_hit(hits: impl IntoIterator<Item = Option<SurfaceHit>>) -> Option<SurfaceHit> {
+    hits.into_iter()
+        .flatten()
+        .min_by(|a, b| a.t.total_cmp(&b.t))
+}
+
+/// Flat annulus in the XZ plane around `center`, facing up. An `inner_radius` of zero gives a
+/// full disk. `u` runs around the center and `v` from the outer to the inner edge.
+pub struct Disk {
+    pub center: Vec3A,
+    pub radius: f32,
+    pub inner_radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Disk {
+    pub fn new(center: Vec3A, radius: f32, inner_radius: f32, material: Arc<dyn Material>) -> Self {
+        assert!(
+            (0.0..radius).contains(&inner_radius),
+            "disk needs an inner radius from zero up to its radius"
+        );
 
-        let v = r.direction.dot(qvec) * inv_det;
-        if v < 0.0 || u + v > 1.0 {
-            // not within bounds of triangle
+        Self {
+            center,
+            radius,
+            inner_radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let t = (self.center.y - r.origin.y) / r.direction.y;
+        if !(t_min..=t_max).contains(&t) {
             return None;
         }
 
-        let t = edge2.dot(qvec) * inv_det;
+        let p = r.at(t) - self.center;
+        let distance = (p.x * p.x + p.z * p.z).sqrt();
+        if distance > self.radius || distance < self.inner_radius {
+            return None;
+        }
 
-        return Some(HitRecord {
-            front_face: true,
-            u,
-            v,
-            p: pvec,
+        Some(HitRecord::new(
+            r,
             t,
-            normal,
-            material: self.material.clone(),
+            Vec3A::Y,
+            azimuth(p),
+            (self.radius - distance) / (self.radius - self.inner_radius),
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let extent = Vec3A::new(self.radius, 0.0, self.radius);
+
+        AABB::new(self.center - extent, self.center + extent)
+    }
+}
+
+/// Cylinder around the y axis, standing on `center` and reaching `height` up, optionally closed
+/// by disks at both ends. On the side `u` runs around the axis and `v` upwards, the caps are
+/// mapped like a `Disk`.
+pub struct Cylinder {
+    pub center: Vec3A,
+    pub radius: f32,
+    pub height: f32,
+    pub capped: bool,
+    pub material: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    pub fn new(
+        center: Vec3A,
+        radius: f32,
+        height: f32,
+        capped: bool,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            radius > 0.0 && height > 0.0,
+            "cylinder needs a positive radius and height"
+        );
+
+        Self {
+            center,
+            radius,
+            height,
+            capped,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let oc = r.origin - self.center;
+        let d = r.direction;
+
+        let side = nearest_root(
+            d.x * d.x + d.z * d.z,
+            2.0 * (oc.x * d.x + oc.z * d.z),
+            oc.x * oc.x + oc.z * oc.z - self.radius * self.radius,
+            t_min,
+            t_max,
+            |t| (0.0..=self.height).contains(&(oc.y + t * d.y)),
+        )
+        .map(|t| {
+            let p = oc + t * d;
+            SurfaceHit {
+                t,
+                outward_normal: Vec3A::new(p.x, 0.0, p.z) / self.radius,
+                u: azimuth(p),
+                v: p.y / self.height,
+            }
         });
+
+        let (bottom, top) = if self.capped {
+            (
+                hit_cap(oc, d, 0.0, self.radius, -Vec3A::Y, t_min, t_max),
+                hit_cap(oc, d, self.height, self.radius, Vec3A::Y, t_min, t_max),
+            )
+        } else {
+            (None, None)
+        };
+
+        nearest_hit([side, bottom, top]).map(|hit| hit.record(r, &self.material))
     }
 
     fn bounding_box(&self) -> AABB {
-        let minimum = self.vertex0.min(self.vertex1.min(self.vertex2));
-        let maximum = self.vertex0.max(self.vertex1.max(self.vertex2));
+        AABB::new(
+            self.center - Vec3A::new(self.radius, 0.0, self.radius),
+            self.center + Vec3A::new(self.radius, self.height, self.radius),
+        )
+    }
+}
 
-        AABB::new(minimum, maximum)
+/// Cone around the y axis with its base of `radius` on `center` and its apex `height` above,
+/// optionally closed at the base. Mapped like a `Cylinder`.
+pub struct Cone {
+    pub center: Vec3A,
+    pub radius: f32,
+    pub height: f32,
+    pub capped: bool,
+    pub material: Arc<dyn Material>,
+}
+
+impl Cone {
+    pub fn new(
+        center: Vec3A,
+        radius: f32,
+        height: f32,
+        capped: bool,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            radius > 0.0 && height > 0.0,
+            "cone needs a positive radius and height"
+        );
+
+        Self {
+            center,
+            radius,
+            height,
+            capped,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let oc = r.origin - self.center;
+        let d = r.direction;
+
+        // x^2 + z^2 = (k (height - y))^2
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let w = self.height - oc.y;
+
+        let side = nearest_root(
+            d.x * d.x + d.z * d.z - k2 * d.y * d.y,
+            2.0 * (oc.x * d.x + oc.z * d.z + k2 * w * d.y),
+            oc.x * oc.x + oc.z * oc.z - k2 * w * w,
+            t_min,
+            t_max,
+            |t| (0.0..=self.height).contains(&(oc.y + t * d.y)),
+        )
+        .map(|t| {
+            let p = oc + t * d;
+            SurfaceHit {
+                t,
+                outward_normal: Vec3A::new(p.x, k2 * (self.height - p.y), p.z)
+                    .try_normalize()
+                    .unwrap_or(Vec3A::Y),
+                u: azimuth(p),
+                v: p.y / self.height,
+            }
+        });
+
+        let base = if self.capped {
+            hit_cap(oc, d, 0.0, self.radius, -Vec3A::Y, t_min, t_max)
+        } else {
+            None
+        };
+
+        nearest_hit([side, base]).map(|hit| hit.record(r, &self.material))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::new(
+            self.center - Vec3A::new(self.radius, 0.0, self.radius),
+            self.center + Vec3A::new(self.radius, self.height, self.radius),
+        )
+    }
+}
+
+/// Paraboloid opening upwards from its vertex at `center`, `radius` wide at `height`, where it
+/// is optionally closed. Mapped like a `Cylinder`.
+pub struct Paraboloid {
+    pub center: Vec3A,
+    pub radius: f32,
+    pub height: f32,
+    pub capped: bool,
+    pub material: Arc<dyn Material>,
+}
+
+impl Paraboloid {
+    pub fn new(
+        center: Vec3A,
+        radius: f32,
+        height: f32,
+        capped: bool,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            radius > 0.0 && height > 0.0,
+            "paraboloid needs a positive radius and height"
+        );
+
+        Self {
+            center,
+            radius,
+            height,
+            capped,
+            material,
+        }
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let oc = r.origin - self.center;
+        let d = r.direction;
+
+        // y = k (x^2 + z^2)
+        let k = self.height / (self.radius * self.radius);
+
+        let side = nearest_root(
+            k * (d.x * d.x + d.z * d.z),
+            2.0 * k * (oc.x * d.x + oc.z * d.z) - d.y,
+            k * (oc.x * oc.x + oc.z * oc.z) - oc.y,
+            t_min,
+            t_max,
+            |t| (0.0..=self.height).contains(&(oc.y + t * d.y)),
+        )
+        .map(|t| {
+            let p = oc + t * d;
+            SurfaceHit {
+                t,
+                outward_normal: Vec3A::new(2.0 * k * p.x, -1.0, 2.0 * k * p.z).normalize(),
+                u: azimuth(p),
+                v: p.y / self.height,
+            }
+        });
+
+        let top = if self.capped {
+            hit_cap(oc, d, self.height, self.radius, Vec3A::Y, t_min, t_max)
+        } else {
+            None
+        };
+
+        nearest_hit([side, top]).map(|hit| hit.record(r, &self.material))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::new(
+            self.center - Vec3A::new(self.radius, 0.0, self.radius),
+            self.center + Vec3A::new(self.radius, self.height, self.radius),
+        )
+    }
+}
+
+/// Torus around the y axis, with a tube of `minor_radius` circling `center` at `major_radius`.
+/// `u` runs around the y axis and `v` around the tube.
+pub struct Torus {
+    pub center: Vec3A,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // The quartic loses too much precision in single precision
+        let oc = (r.origin - self.center).as_dvec3();
+        let d = r.direction.as_dvec3();
+        let major2 = (self.major_radius as f64).powi(2);
+        let minor2 = (self.minor_radius as f64).powi(2);
+
+        let dd = d.dot(d);
+        let od = oc.dot(d);
+        let e = oc.dot(oc) - major2 - minor2;
+
+        let roots = solve_quartic(
+            dd * dd,
+            4.0 * dd * od,
+            2.0 * dd * e + 4.0 * od * od + 4.0 * major2 * d.y * d.y,
+            4.0 * od * e + 8.0 * major2 * oc.y * d.y,
+            e * e - 4.0 * major2 * (minor2 - oc.y * oc.y),
+        );
+
+        let t = roots
+            .into_iter()
+            .map(|t| t as f32)
+            .filter(|t| (t_min..=t_max).contains(t))
+            .min_by(f32::total_cmp)?;
+
+        let p = r.at(t) - self.center;
+        let ring = Vec3A::new(p.x, 0.0, p.z).normalize_or_zero() * self.major_radius;
+        let tube = p - ring;
+
+        let u = azimuth(p);
+        let v = (tube.y.atan2(ring.dot(tube) / self.major_radius) + PI) / (2.0 * PI);
+
+        Some(HitRecord::new(r, t, tube.normalize(), u, v, &self.material))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let extent = Vec3A::new(
+            self.major_radius + self.minor_radius,
+            self.minor_radius,
+            self.major_radius + self.minor_radius,
+        );
+
+        AABB::new(self.center - extent, self.center + extent)
     }
 }
@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use glam::Vec3A;
+
+use crate::{
+    aabb::AABB,
+    geometry::intersect_triangle,
+    hittable::Hittable,
+    material::Material,
+    ray::{HitRecord, Ray},
+    texture::image::ImageTexture,
+};
+
+/// Terrain given by heights on a regular grid over the xz plane, every cell split into two
+/// triangles. Rays walk the cells they pass over in order, so only the triangles below the ray
+/// are tested and the first hit found is the closest one.
+pub struct Heightfield {
+    /// Heights at the grid vertices from 0 to 1, in rows along x.
+    heights: Vec<f32>,
+    normals: Vec<Vec3A>,
+    /// Vertices along x and z.
+    nx: usize,
+    nz: usize,
+    /// Corner of the grid with the lowest coordinates, at height 0.
+    origin: Vec3A,
+    /// Extent of the grid along x and z, and the height of the highest possible point.
+    size: Vec3A,
+    pub material: Arc<dyn Material>,
+    aabb: AABB,
+}
+
+impl Heightfield {
+    pub fn new(
+        heights: Vec<f32>,
+        nx: usize,
+        nz: usize,
+        origin: Vec3A,
+        size: Vec3A,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            nx >= 2 && nz >= 2,
+            "heightfield needs at least 2 by 2 vertices"
+        );
+        assert_eq!(heights.len(), nx * nz, "heights don't match the grid");
+
+        let (low, high) = heights
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(low, high), &h| {
+                (low.min(h), high.max(h))
+            });
+        let aabb = AABB::new(
+            Vec3A::new(origin.x, origin.y + low * size.y, origin.z),
+            Vec3A::new(
+                origin.x + size.x,
+                origin.y + high * size.y,
+                origin.z + size.z,
+            ),
+        );
+
+        let mut heightfield = Self {
+            heights,
+            normals: vec![],
+            nx,
+            nz,
+            origin,
+            size,
+            material,
+            aabb,
+        };
+        heightfield.normals = (0..nz)
+            .flat_map(|j| (0..nx).map(move |i| (i, j)))
+            .map(|(i, j)| heightfield.vertex_normal(i, j))
+            .collect();
+
+        heightfield
+    }
+
+    /// Creates a heightfield from an image, with a vertex for every pixel and the brightness of
+    /// the pixel as height. The top row of the image lies along the lowest z.
+    pub fn from_image(
+        image: &ImageTexture,
+        origin: Vec3A,
+        size: Vec3A,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let heights = image
+            .data
+            .chunks(3)
+            .map(|pixel| pixel.iter().map(|&c| c as f32).sum::<f32>() / (3.0 * 255.0))
+            .collect();
+
+        Self::new(heights, image.width, image.height, origin, size, material)
+    }
+
+    fn spacing(&self) -> (f32, f32) {
+        (
+            self.size.x / (self.nx - 1) as f32,
+            self.size.z / (self.nz - 1) as f32,
+        )
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Vec3A {
+        let (dx, dz) = self.spacing();
+
+        self.origin
+            + Vec3A::new(
+                i as f32 * dx,
+                self.heights[j * self.nx + i] * self.size.y,
+                j as f32 * dz,
+            )
+    }
+
+    /// Normal from the slope between the neighbouring vertices, one-sided at the borders.
+    fn vertex_normal(&self, i: usize, j: usize) -> Vec3A {
+        let (i0, i1) = (i.saturating_sub(1), (i + 1).min(self.nx - 1));
+        let (j0, j1) = (j.saturating_sub(1), (j + 1).min(self.nz - 1));
+
+        let along_x = self.vertex(i1, j) - self.vertex(i0, j);
+        let along_z = self.vertex(i, j1) - self.vertex(i, j0);
+
+        along_z.cross(along_x).normalize()
+    }
+
+    /// Height of the highest corner of cell `(i, j)`.
+    fn cell_top(&self, i: usize, j: usize) -> f32 {
+        let top = [(i, j), (i, j + 1), (i + 1, j + 1), (i + 1, j)]
+            .iter()
+            .map(|&(i, j)| self.heights[j * self.nx + i])
+            .fold(f32::MIN, f32::max);
+
+        self.origin.y + top * self.size.y
+    }
+
+    /// Intersects the two triangles of cell `(i, j)`.
+    fn hit_cell(&self, r: &Ray, i: usize, j: usize, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let corners = [(i, j), (i, j + 1), (i + 1, j + 1), (i + 1, j)];
+        let triangles = [
+            [corners[0], corners[1], corners[2]],
+            [corners[0], corners[2], corners[3]],
+        ];
+
+        let mut closest = None;
+        for (index, triangle) in triangles.iter().enumerate() {
+            let t_max = closest.map_or(t_max, |((t, _, _), _)| t);
+            let [v0, v1, v2] = triangle.map(|(i, j)| self.vertex(i, j));
+
+            if let Some(hit) = intersect_triangle(r, v0, v1, v2, t_min, t_max) {
+                closest = Some((hit, index));
+            }
+        }
+
+        let ((t, u, v), index) = closest?;
+        let triangle = triangles[index];
+        let [v0, v1, v2] = triangle.map(|(i, j)| self.vertex(i, j));
+        let [n0, n1, n2] = triangle.map(|(i, j)| self.normals[j * self.nx + i]);
+
+        let p = r.at(t);
+        let tex_u = (p.x - self.origin.x) / self.size.x;
+        let tex_v = 1.0 - (p.z - self.origin.z) / self.size.z;
+
+        // Which side is hit follows the triangle, shading the interpolated vertex normals
+        let outward_normal = (v1 - v0).cross(v2 - v0).normalize();
+        let mut rec = HitRecord::new(r, t, outward_normal, tex_u, tex_v, &self.material);
+        let normal = ((1.0 - u - v) * n0 + u * n1 + v * n2).normalize();
+        rec.normal = if rec.front_face { normal } else { -normal };
+
+        Some(rec)
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (enter, exit) = r.aabb_interval(self.aabb)?;
+        let t_start = enter.max(t_min);
+        let t_end = exit.min(t_max);
+        if t_start > t_end {
+            return None;
+        }
+
+        let (dx, dz) = self.spacing();
+        let start = r.at(t_start) - self.origin;
+        let mut i = ((start.x / dx) as isize).clamp(0, self.nx as isize - 2);
+        let mut j = ((start.z / dz) as isize).clamp(0, self.nz as isize - 2);
+
+        // Ray parameters at the next cell borders along x and z, and between borders
+        let axis = |cell: isize, spacing: f32, origin: f32, ray_origin: f32, direction: f32| {
+            if direction == 0.0 {
+                return (0, f32::INFINITY, f32::INFINITY);
+            }
+
+            let step = if direction > 0.0 { 1 } else { -1 };
+            let border = origin + (cell + (step > 0) as isize) as f32 * spacing;
+
+            (
+                step,
+                (border - ray_origin) / direction,
+                spacing / direction.abs(),
+            )
+        };
+        let (step_x, mut next_x, delta_x) = axis(i, dx, self.origin.x, r.origin.x, r.direction.x);
+        let (step_z, mut next_z, delta_z) = axis(j, dz, self.origin.z, r.origin.z, r.direction.z);
+
+        let mut t_cell = t_start;
+
+        loop {
+            // Skip cells the ray passes entirely above
+            let t_leave = next_x.min(next_z).min(t_end);
+            let lowest = r.at(t_cell).y.min(r.at(t_leave).y);
+            if lowest <= self.cell_top(i as usize, j as usize) {
+                if let Some(rec) = self.hit_cell(r, i as usize, j as usize, t_min, t_max) {
+                    return Some(rec);
+                }
+            }
+            t_cell = t_leave;
+
+            if next_x < next_z {
+                if next_x > t_end {
+                    return None;
+                }
+                i += step_x;
+                next_x += delta_x;
+            } else {
+                if next_z > t_end {
+                    return None;
+                }
+                j += step_z;
+                next_z += delta_z;
+            }
+
+            if i < 0 || j < 0 || i > self.nx as isize - 2 || j > self.nz as isize - 2 {
+                return None;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
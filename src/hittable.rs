@@ -1,9 +1,59 @@
+use std::sync::Arc;
+
 use crate::{
     aabb::AABB,
     ray::{HitRecord, Ray},
 };
 
+/// Distance to step past a hit when looking for the next one along the ray, relative to the
+/// distance of the hit so that far away hits are stepped over too.
+const HIT_ALL_EPSILON: f32 = 1e-4;
+
 pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
     fn bounding_box(&self) -> AABB;
+
+    /// Returns all intersections with the ray between `t_min` and `t_max`, nearest first. On a
+    /// closed object they alternate between entering and leaving, as told by `front_face`.
+    /// Objects whose intersections can be found at once may override this, by default the
+    /// ray is intersected again past every hit.
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        let mut hits = vec![];
+        let mut t = t_min;
+
+        while let Some(rec) = self.hit(r, t, t_max) {
+            t = rec.t + HIT_ALL_EPSILON * rec.t.max(1.0);
+            hits.push(rec);
+        }
+
+        hits
+    }
+}
+
+impl<H: Hittable + ?Sized> Hittable for Box<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        (**self).bounding_box()
+    }
+
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        (**self).hit_all(r, t_min, t_max)
+    }
+}
+
+impl<H: Hittable + ?Sized> Hittable for Arc<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        (**self).bounding_box()
+    }
+
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        (**self).hit_all(r, t_min, t_max)
+    }
 }
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use glam::{Affine3A, Mat3A, Quat, Vec3, Vec3A};
+
+use crate::{
+    aabb::AABB,
+    hittable::Hittable,
+    ray::{HitRecord, Ray},
+};
+
+/// Maps the ray into object space by `inverse`. The direction is not normalized, so distances
+/// along the ray are the same in both spaces.
+fn local_ray(inverse: &Affine3A, r: &Ray) -> Ray {
+    Ray {
+        origin: inverse.transform_point3a(r.origin),
+        direction: inverse.transform_vector3a(r.direction),
+        time: r.time,
+    }
+}
+
+/// Brings a hit in object space back into world space.
+fn to_world(mut rec: HitRecord, r: &Ray, normal_matrix: &Mat3A) -> HitRecord {
+    rec.p = r.at(rec.t);
+    rec.normal = (*normal_matrix * rec.normal).normalize();
+    rec
+}
+
+/// Places a shared object in the scene through an affine transform. The object, typically a
+/// `TriangleMesh` with its own bottom-level BVH, is stored once and referenced by any number of
+/// instances, which the scene BVH then organises as the top level.
+pub struct Instance {
+    pub object: Arc<dyn Hittable>,
+    inverse: Affine3A,
+    normal_matrix: Mat3A,
+    aabb: AABB,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Affine3A) -> Self {
+        let inverse = transform.inverse();
+
+        Self {
+            aabb: object.bounding_box().transform(&transform),
+            normal_matrix: inverse.matrix3.transpose(),
+            object,
+            inverse,
+        }
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let rec = self
+            .object
+            .hit(&local_ray(&self.inverse, r), t_min, t_max)?;
+
+        Some(to_world(rec, r, &self.normal_matrix))
+    }
+
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        self.object
+            .hit_all(&local_ray(&self.inverse, r), t_min, t_max)
+            .into_iter()
+            .map(|rec| to_world(rec, r, &self.normal_matrix))
+            .collect()
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
+
+/// Transform of an animated instance at a point in time, kept decomposed so that rotations can
+/// be interpolated along the shortest arc.
+#[derive(Clone, Copy, Debug)]
+pub struct TransformKeyframe {
+    pub time: f32,
+    pub scale: Vec3,
+    pub rotation: Quat,
+    pub translation: Vec3,
+}
+
+impl TransformKeyframe {
+    pub fn new(time: f32, transform: Affine3A) -> Self {
+        let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+
+        Self {
+            time,
+            scale,
+            rotation,
+            translation,
+        }
+    }
+
+    pub fn interpolate(&self, other: &TransformKeyframe, time: f32) -> Affine3A {
+        let s = ((time - self.time) / (other.time - self.time)).clamp(0.0, 1.0);
+
+        Affine3A::from_scale_rotation_translation(
+            self.scale.lerp(other.scale, s),
+            self.rotation.slerp(other.rotation, s),
+            self.translation.lerp(other.translation, s),
+        )
+    }
+
+    pub fn transform(&self) -> Affine3A {
+        Affine3A::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+/// Instance whose transform is interpolated between keyframes at the time of each ray, which
+/// gives motion blur for rigidly moving objects. Before the first and after the last keyframe
+/// the object stays put.
+pub struct AnimatedInstance {
+    pub object: Arc<dyn Hittable>,
+    keyframes: Vec<TransformKeyframe>,
+    aabb: AABB,
+}
+
+impl AnimatedInstance {
+    /// Number of points in time the bounding box is evaluated at between two keyframes.
+    const BOUND_STEPS: usize = 16;
+
+    pub fn new(object: Arc<dyn Hittable>, mut keyframes: Vec<TransformKeyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "animated instance without keyframes");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        assert!(
+            keyframes.windows(2).all(|pair| pair[0].time < pair[1].time),
+            "animated instance with two keyframes at the same time"
+        );
+
+        let object_aabb = object.bounding_box();
+        let mut aabb = object_aabb.transform(&keyframes[0].transform());
+
+        for pair in keyframes.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+
+            // Corners move along arcs while rotating, pad the sampled boxes by the largest
+            // distance an arc can bulge out between two samples.
+            let angle = from.rotation.angle_between(to.rotation) / Self::BOUND_STEPS as f32;
+            let radius = object_aabb
+                .minimum
+                .abs()
+                .max(object_aabb.maximum.abs())
+                .length()
+                * from.scale.max(to.scale).max_element();
+            let padding = Vec3A::splat(radius * (1.0 - (angle / 2.0).cos()));
+
+            for step in 1..=Self::BOUND_STEPS {
+                let time =
+                    from.time + (to.time - from.time) * step as f32 / Self::BOUND_STEPS as f32;
+                let step_aabb = object_aabb.transform(&from.interpolate(to, time));
+
+                aabb.join_mut(&AABB::new(
+                    step_aabb.minimum - padding,
+                    step_aabb.maximum + padding,
+                ));
+            }
+        }
+
+        Self {
+            object,
+            keyframes,
+            aabb,
+        }
+    }
+
+    pub fn transform_at(&self, time: f32) -> Affine3A {
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+
+        if next == 0 {
+            self.keyframes[0].transform()
+        } else if next == self.keyframes.len() {
+            self.keyframes[next - 1].transform()
+        } else {
+            self.keyframes[next - 1].interpolate(&self.keyframes[next], time)
+        }
+    }
+}
+
+impl Hittable for AnimatedInstance {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let inverse = self.transform_at(r.time).inverse();
+        let rec = self.object.hit(&local_ray(&inverse, r), t_min, t_max)?;
+
+        Some(to_world(rec, r, &inverse.matrix3.transpose()))
+    }
+
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        let inverse = self.transform_at(r.time).inverse();
+        let normal_matrix = inverse.matrix3.transpose();
+
+        self.object
+            .hit_all(&local_ray(&inverse, r), t_min, t_max)
+            .into_iter()
+            .map(|rec| to_world(rec, r, &normal_matrix))
+            .collect()
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
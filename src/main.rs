@@ -1,31 +1,57 @@
 #![cfg_attr(feature = "bench", feature(test))]
 
-use glam::Vec3A;
+use glam::{Vec3A, Vec4};
 use rayon::prelude::*;
-use std::{fs::File, io::BufWriter, path::Path, time::Instant, vec};
+use std::{
+    fs::File,
+    io::BufWriter,
+    ops::{Add, Mul},
+    path::Path,
+    sync::Arc,
+    time::Instant,
+    vec,
+};
 
 use camera::Camera;
+use hittable::Hittable;
 use indicatif::ProgressBar;
+use medium::{Medium, MediumSample};
 use rand::Rng;
-use ray::Ray;
+use ray::{HitRecord, Ray};
 use scene::Scene;
+use spectrum::SampledWavelengths;
 use vec3::unit_vector;
 
 use crate::bvh::sah::BVH;
 
 mod aabb;
+mod animation;
 mod axis;
 mod bvh;
 mod camera;
+mod csg;
+mod curve;
+mod displacement;
 mod geometry;
+mod heightfield;
 mod hittable;
+mod instance;
 mod material;
+mod medium;
+mod mesh;
+mod microfacet;
+mod patch;
 mod perlin;
 mod ray;
 mod scene;
+mod sdf;
+mod spectrum;
+mod subdivision;
 mod texture;
+mod transform;
 mod util;
 mod vec3;
+mod volume;
 
 #[cfg(all(feature = "bench", test))]
 extern crate test;
@@ -36,53 +62,251 @@ mod tests;
 const MAX_DEPTH: u32 = 16;
 const SAMPLES_PER_PIXEL: u32 = 80;
 
-fn main() {
-    let path = Path::new("image.png");
-    let file = File::create(path).unwrap();
-    let w = BufWriter::new(file);
+/// A demo scene `--scene` selects, with the view it is meant to be seen from.
+struct DemoScene {
+    name: &'static str,
+    build: fn(&mut Scene) -> &mut Scene,
+    lookfrom: Vec3A,
+    lookat: Vec3A,
+    /// Vertical field of view in degrees.
+    vfov: f32,
+}
+
+const SCENES: &[DemoScene] = &[
+    DemoScene {
+        name: "random",
+        build: Scene::randomize,
+        lookfrom: Vec3A::new(13.0, 2.0, 3.0),
+        lookat: Vec3A::ZERO,
+        vfov: 20.0,
+    },
+    DemoScene {
+        name: "bunnies",
+        build: Scene::randomize_bunnies,
+        lookfrom: Vec3A::new(13.0, 2.0, 3.0),
+        lookat: Vec3A::ZERO,
+        vfov: 20.0,
+    },
+    DemoScene {
+        name: "motion_blur",
+        build: Scene::motion_blur,
+        lookfrom: Vec3A::new(13.0, 2.0, 3.0),
+        lookat: Vec3A::ZERO,
+        vfov: 20.0,
+    },
+    DemoScene {
+        name: "cornell_box",
+        build: Scene::cornell_box,
+        lookfrom: Vec3A::new(278.0, 278.0, -800.0),
+        lookat: Vec3A::new(278.0, 278.0, 0.0),
+        vfov: 40.0,
+    },
+    DemoScene {
+        name: "cornell_smoke",
+        build: Scene::cornell_smoke,
+        lookfrom: Vec3A::new(278.0, 278.0, -800.0),
+        lookat: Vec3A::new(278.0, 278.0, 0.0),
+        vfov: 40.0,
+    },
+    DemoScene {
+        name: "quadrics",
+        build: Scene::quadrics,
+        lookfrom: Vec3A::new(0.0, 3.0, 12.0),
+        lookat: Vec3A::new(0.0, 1.0, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "csg",
+        build: Scene::csg,
+        lookfrom: Vec3A::new(0.0, 3.0, 12.0),
+        lookat: Vec3A::new(0.0, 1.0, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "sdf",
+        build: Scene::sdf,
+        lookfrom: Vec3A::new(0.0, 3.0, 12.0),
+        lookat: Vec3A::new(0.0, 1.0, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "grass",
+        build: Scene::grass,
+        lookfrom: Vec3A::new(0.0, 1.5, 6.0),
+        lookat: Vec3A::new(0.0, 0.6, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "patches",
+        build: Scene::patches,
+        lookfrom: Vec3A::new(0.0, 4.0, 8.0),
+        lookat: Vec3A::new(0.0, 0.5, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "subdivision",
+        build: Scene::subdivision,
+        lookfrom: Vec3A::new(0.0, 4.0, 9.0),
+        lookat: Vec3A::new(0.0, 0.8, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "displacement",
+        build: Scene::displacement,
+        lookfrom: Vec3A::new(0.0, 4.0, 4.0),
+        lookat: Vec3A::new(0.0, 0.3, 0.0),
+        vfov: 40.0,
+    },
+    DemoScene {
+        name: "heightfield",
+        build: Scene::heightfield,
+        lookfrom: Vec3A::new(0.0, 4.0, 4.0),
+        lookat: Vec3A::new(0.0, 0.3, 0.0),
+        vfov: 40.0,
+    },
+    DemoScene {
+        name: "clouds",
+        build: Scene::clouds,
+        lookfrom: Vec3A::new(0.0, 2.0, 9.0),
+        lookat: Vec3A::new(0.0, 1.2, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "fog",
+        build: Scene::fog,
+        lookfrom: Vec3A::new(0.0, 1.5, 8.0),
+        lookat: Vec3A::new(0.0, 1.0, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "colored_glass",
+        build: Scene::colored_glass,
+        lookfrom: Vec3A::new(0.0, 2.0, 9.0),
+        lookat: Vec3A::new(0.0, 0.8, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "prism",
+        build: Scene::prism,
+        lookfrom: Vec3A::new(0.0, 1.0, 6.0),
+        lookat: Vec3A::new(0.0, 1.0, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "metals",
+        build: Scene::metals,
+        lookfrom: Vec3A::new(0.0, 3.0, 10.0),
+        lookat: Vec3A::new(0.0, 1.0, 0.0),
+        vfov: 30.0,
+    },
+    DemoScene {
+        name: "frosted_glass",
+        build: Scene::frosted_glass,
+        lookfrom: Vec3A::new(0.0, 1.0, 8.0),
+        lookat: Vec3A::new(0.0, 0.6, 0.0),
+        vfov: 30.0,
+    },
+];
 
+fn main() {
     let aspect_ratio = 3.0 / 2.0;
 
     let image_width: u32 = 1040;
     let image_height: u32 = (image_width as f32 / aspect_ratio) as u32;
 
-    let lookfrom = Vec3A::new(13.0, 2.0, 3.0);
-    let lookat = Vec3A::new(0.0, 0.0, 0.0);
-
     println!(
         "Configuration:\ndepth: {}, samples: {}, image_size:{}x{}",
         MAX_DEPTH, SAMPLES_PER_PIXEL, image_width, image_height
     );
 
-    let camera = Camera::new(
-        lookfrom,
-        lookat,
-        Vec3A::new(0.0, 1.0, 0.0),
-        20.0,
-        aspect_ratio,
-        0.01,
-        10.0,
-    );
+    let args: Vec<String> = std::env::args().collect();
 
-    let mut encoder = png::Encoder::new(w, image_width, image_height); // Width is 2 pixels and height is 1.
-    encoder.set_color(png::ColorType::Rgb);
-    encoder.set_depth(png::BitDepth::Eight);
-    encoder.set_compression(png::Compression::Fast);
+    // `--spectral` traces wavelengths instead of RGB, for dispersion
+    let spectral = args.iter().any(|arg| arg == "--spectral");
 
-    let mut writer = encoder.write_header().unwrap();
+    // `--frames <first> <last>` renders the animation to numbered images instead
+    if let Some(index) = args.iter().position(|arg| arg == "--frames") {
+        let frame_arg = |offset: usize| args.get(index + offset)?.parse::<u32>().ok();
+        let (Some(first), Some(last)) = (frame_arg(1), frame_arg(2)) else {
+            eprintln!("usage: {} --frames <first> <last> [--spectral]", args[0]);
+            return;
+        };
 
-    let mut data: Vec<u8> = vec![];
+        let animation = Scene::turntable(aspect_ratio);
 
-    let bar = ProgressBar::new((image_height * image_width).into());
+        for frame in first..=last {
+            let (camera, objects) = animation.frame(frame);
+
+            let mut scene = Scene::new();
+            scene.set(objects);
+            scene.unbounded = animation
+                .unbounded
+                .iter()
+                .map(|object| Box::new(object.clone()) as Box<dyn Hittable>)
+                .collect();
+
+            // Only the few animated objects and the prebuilt static part are in this BVH
+            let bvh = BVH::build(&scene.objects);
+
+            let data = render(&scene, &bvh, &camera, image_width, image_height, spectral);
+            let path = format!("frame_{:04}.png", frame);
+            write_png(Path::new(&path), image_width, image_height, &data);
+        }
+
+        return;
+    }
+
+    // `--scene <name>` renders one of the demo scenes instead of the bunnies
+    let name = match args.iter().position(|arg| arg == "--scene") {
+        Some(index) => args.get(index + 1).map(String::as_str),
+        None => Some("bunnies"),
+    };
+    let Some(demo) = SCENES.iter().find(|demo| Some(demo.name) == name) else {
+        let names: Vec<&str> = SCENES.iter().map(|demo| demo.name).collect();
+        eprintln!(
+            "usage: {} --scene <{}> [--spectral]",
+            args[0],
+            names.join("|")
+        );
+        return;
+    };
+
+    let camera = Camera::new(
+        demo.lookfrom,
+        demo.lookat,
+        Vec3A::new(0.0, 1.0, 0.0),
+        demo.vfov,
+        aspect_ratio,
+        0.01,
+        (demo.lookfrom - demo.lookat).length(),
+    )
+    .with_shutter(0.0, 1.0);
 
     let mut scene = Scene::new();
     //let mut scene = Scene::from_obj("bunny.obj".to_string());
 
-    scene.randomize_bunnies();
+    (demo.build)(&mut scene);
 
     let bvh = BVH::build(&scene.objects);
     //bvh.pretty_print();
 
+    let data = render(&scene, &bvh, &camera, image_width, image_height, spectral);
+
+    write_png(Path::new("image.png"), image_width, image_height, &data);
+}
+
+fn render(
+    scene: &Scene,
+    bvh: &BVH,
+    camera: &Camera,
+    image_width: u32,
+    image_height: u32,
+    spectral: bool,
+) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![];
+
+    let bar = ProgressBar::new((image_height * image_width).into());
+
     let mut pixelvecs: Vec<Vec<Vec3A>> = vec![];
 
     let now = Instant::now();
@@ -104,7 +328,21 @@ fn main() {
 
                         let r: Ray = camera.get_ray(u, v);
 
-                        let color = ray_color(&scene, &r, &bvh, MAX_DEPTH);
+                        let color = if spectral {
+                            let mut wavelengths = SampledWavelengths::sample(rng.gen());
+                            let values = ray_color(
+                                scene,
+                                &r,
+                                bvh,
+                                MAX_DEPTH,
+                                scene.medium.as_ref(),
+                                &mut wavelengths,
+                            );
+
+                            wavelengths.to_rgb(values)
+                        } else {
+                            ray_color(scene, &r, bvh, MAX_DEPTH, scene.medium.as_ref(), &mut Rgb)
+                        };
                         pixel_color += color;
                     }
                     bar.inc(1);
@@ -129,41 +367,183 @@ fn main() {
     }
     bar.finish();
 
-    writer.write_image_data(&data).unwrap();
+    data
 }
 
-fn ray_color(scene: &Scene, r: &Ray, bvh: &BVH, depth: u32) -> Vec3A {
+fn write_png(path: &Path, image_width: u32, image_height: u32, data: &[u8]) {
+    let file = File::create(path).unwrap();
+    let w = BufWriter::new(file);
+
+    let mut encoder = png::Encoder::new(w, image_width, image_height); // Width is 2 pixels and height is 1.
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(png::Compression::Fast);
+
+    let mut writer = encoder.write_header().unwrap();
+
+    writer.write_image_data(data).unwrap();
+}
+
+/// How the integrator carries light along a path, as RGB colors or as values at a few sampled
+/// wavelengths.
+trait Radiance {
+    type Value: Copy + Add<Output = Self::Value> + Mul<Output = Self::Value>;
+
+    /// The value carrying an RGB color, like a reflectance or the light of the background.
+    fn lift(&self, rgb: Vec3A) -> Self::Value;
+
+    fn emitted(&self, rec: &HitRecord) -> Self::Value;
+
+    fn scatter(&mut self, r: &Ray, rec: &HitRecord) -> Result<(Self::Value, Ray), ()>;
+}
+
+/// Light traced in RGB.
+struct Rgb;
+
+impl Radiance for Rgb {
+    type Value = Vec3A;
+
+    fn lift(&self, rgb: Vec3A) -> Vec3A {
+        rgb
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Vec3A {
+        rec.material.emitted(rec.u, rec.v, rec.p)
+    }
+
+    fn scatter(&mut self, r: &Ray, rec: &HitRecord) -> Result<(Vec3A, Ray), ()> {
+        rec.material.scatter(r, rec)
+    }
+}
+
+/// Light traced at the sampled wavelengths. Materials may drop all but the hero wavelength on
+/// the way.
+impl Radiance for SampledWavelengths {
+    type Value = Vec4;
+
+    fn lift(&self, rgb: Vec3A) -> Vec4 {
+        self.uplift(rgb)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Vec4 {
+        rec.material.emitted_spectral(rec.u, rec.v, rec.p, self)
+    }
+
+    fn scatter(&mut self, r: &Ray, rec: &HitRecord) -> Result<(Vec4, Ray), ()> {
+        rec.material.scatter_spectral(r, rec, self)
+    }
+}
+
+/// Light arriving along the ray, which travels through `medium`.
+fn ray_color<L: Radiance>(
+    scene: &Scene,
+    r: &Ray,
+    bvh: &BVH,
+    depth: u32,
+    medium: Option<&Arc<dyn Medium>>,
+    light: &mut L,
+) -> L::Value {
     if depth == 0 {
-        return Vec3A::new(0.0, 0.0, 0.0);
+        return light.lift(Vec3A::ZERO);
     }
 
-    let shapes = bvh.traverse(r, &scene.objects);
+    let mut shapes = bvh.traverse(r, &scene.objects);
+    shapes.extend(&scene.unbounded);
 
     let hit_record = r.hit(shapes);
-    match hit_record {
-        Some(t) => {
-            let scattered = t.material.scatter(r, &t);
-            match scattered {
-                Ok(scattered_ray) => {
-                    scattered_ray.0 * ray_color(scene, &scattered_ray.1, bvh, depth - 1)
-                }
-                Err(_) => Vec3A::new(0.0, 0.0, 0.0),
+
+    // The ray may scatter in the medium before it reaches the surface. Media end at the last
+    // surface, so that light from the background reaches scenes filled with fog.
+    let mut weight = light.lift(Vec3A::ONE);
+    if let (Some(medium), Some(rec)) = (medium, &hit_record) {
+        match medium.sample(r, rec.t) {
+            MediumSample::Scatter { rec, weight } => {
+                let weight = light.lift(weight);
+                let emitted = light.emitted(&rec);
+
+                return weight
+                    * match light.scatter(r, &rec) {
+                        Ok((attenuation, scattered)) => {
+                            emitted
+                                + attenuation
+                                    * ray_color(
+                                        scene,
+                                        &scattered,
+                                        bvh,
+                                        depth - 1,
+                                        Some(medium),
+                                        light,
+                                    )
+                        }
+                        Err(_) => emitted,
+                    };
             }
+            MediumSample::Pass { weight: pass } => weight = light.lift(pass),
         }
-        _ => {
-            let unit_direction = unit_vector(r.direction);
-            let t = 0.5 * (unit_direction.y + 1.0);
-
-            Vec3A::new(1.0, 1.0, 1.0) * (1.0 - t) + t * Vec3A::new(0.5, 0.7, 1.0)
+    }
 
-            /*let tex = TightCheckerTexture::new_from_colors(
-                Vec3A::new(0.2, 0.3, 0.1),
-                Vec3A::new(0.9, 0.9, 0.9),
-            );
+    let Some(t) = hit_record else {
+        return weight * light.lift(background(scene, r));
+    };
 
-            let uv = Sphere::get_sphere_uv(unit_vector(r.direction));
+    let emitted = light.emitted(&t);
+    weight
+        * match light.scatter(r, &t) {
+            Ok((attenuation, scattered)) => {
+                let medium = next_medium(scene, &t, &scattered, medium);
 
-            tex.value(uv.0, uv.1, unit_vector(r.direction))*/
+                emitted + attenuation * ray_color(scene, &scattered, bvh, depth - 1, medium, light)
+            }
+            Err(_) => emitted,
         }
+}
+
+/// Medium the ray scattered at a surface travels through. Rays scattered through a surface with
+/// media, or of a material filled with one, enter the one on that side, other surfaces leave the
+/// ray in the `medium` it was in.
+fn next_medium<'a>(
+    scene: &'a Scene,
+    rec: &'a HitRecord,
+    scattered: &Ray,
+    medium: Option<&'a Arc<dyn Medium>>,
+) -> Option<&'a Arc<dyn Medium>> {
+    let (interior, exterior) = match (&rec.media, rec.material.interior()) {
+        (Some(media), _) => (media.interior.as_ref(), media.exterior.as_ref()),
+        (None, Some(interior)) => (Some(interior), None),
+        (None, None) => return medium,
+    };
+
+    let outward_normal = if rec.front_face {
+        rec.normal
+    } else {
+        -rec.normal
+    };
+    let side = if scattered.direction.dot(outward_normal) < 0.0 {
+        interior
+    } else {
+        exterior
+    };
+
+    side.or(scene.medium.as_ref())
+}
+
+/// Color of the light arriving along a ray that leaves the scene.
+fn background(scene: &Scene, r: &Ray) -> Vec3A {
+    if let Some(background) = scene.background {
+        return background;
     }
+
+    let unit_direction = unit_vector(r.direction);
+    let t = 0.5 * (unit_direction.y + 1.0);
+
+    Vec3A::new(1.0, 1.0, 1.0) * (1.0 - t) + t * Vec3A::new(0.5, 0.7, 1.0)
+
+    /*let tex = TightCheckerTexture::new_from_colors(
+        Vec3A::new(0.2, 0.3, 0.1),
+        Vec3A::new(0.9, 0.9, 0.9),
+    );
+
+    let uv = Sphere::get_sphere_uv(unit_vector(r.direction));
+
+    tex.value(uv.0, uv.1, unit_vector(r.direction))*/
 }
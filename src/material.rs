@@ -1,16 +1,46 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
-use glam::Vec3A;
+use glam::{Vec3A, Vec4};
 use rand::Rng;
 
 use crate::{
+    medium::{HomogeneousMedium, Medium},
+    microfacet::{fresnel_dielectric, reflect, schlick, Frame, TrowbridgeReitz},
     ray::{HitRecord, Ray},
+    spectrum::SampledWavelengths,
     texture::Texture,
-    vec3::{near_zero, random_in_unit_sphere, random_unit_vector, refract, unit_vector},
+    vec3::{near_zero, random_unit_vector, refract, unit_vector},
 };
 
 pub trait Material: Send + Sync + Debug {
     fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Result<(Vec3A, Ray), ()>;
+
+    fn emitted(&self, _u: f32, _v: f32, _p: Vec3A) -> Vec3A {
+        Vec3A::new(0.0, 0.0, 0.0)
+    }
+
+    /// Scatters light of the sampled wavelengths, by default like `scatter` with the
+    /// attenuation turned into a spectrum. Materials depending on the wavelength override it.
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        wavelengths: &mut SampledWavelengths,
+    ) -> Result<(Vec4, Ray), ()> {
+        let (attenuation, scattered) = self.scatter(ray, rec)?;
+
+        Ok((wavelengths.uplift(attenuation), scattered))
+    }
+
+    fn emitted_spectral(&self, u: f32, v: f32, p: Vec3A, wavelengths: &SampledWavelengths) -> Vec4 {
+        wavelengths.uplift(self.emitted(u, v, p))
+    }
+
+    /// Medium filling objects of this material, which rays scattered into them travel through.
+    /// Media attached to the object with `WithMedia` take precedence.
+    fn interior(&self) -> Option<&Arc<dyn Medium>> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -36,36 +66,144 @@ impl Material for Lambertian {
     }
 }
 
+/// Conductor reflecting off microfacets of the GGX distribution, with `albedo` as the color
+/// reflected at normal incidence. The reflection brightens towards white at grazing angles
+/// by the Fresnel equations, and light is only lost where rough facets shadow each other.
 #[derive(Debug)]
 pub struct Metal {
     pub albedo: Vec3A,
-    pub fuzz: f32,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl Metal {
+    /// Creates a metal from a `roughness` from 0 for a mirror to 1.
+    pub fn new(albedo: Vec3A, roughness: f32) -> Self {
+        Self {
+            albedo,
+            distribution: TrowbridgeReitz::new(roughness, 0.0),
+        }
+    }
+
+    /// Creates a brushed metal, its grooves running around the y axis and its highlights
+    /// stretched across them by an `anisotropy` from 0 to 1.
+    pub fn anisotropic(albedo: Vec3A, roughness: f32, anisotropy: f32) -> Self {
+        Self {
+            albedo,
+            distribution: TrowbridgeReitz::new(roughness, anisotropy),
+        }
+    }
 }
 
 impl Material for Metal {
     fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Result<(Vec3A, Ray), ()> {
-        let uvec = unit_vector(ray.direction);
-        let reflected = uvec - 2.0 * uvec.dot(rec.normal) * rec.normal;
+        let frame = Frame::around_y(rec.normal);
+        let wo = frame.to_local(-unit_vector(ray.direction));
+        if wo.z <= 0.0 {
+            return Err(());
+        }
+
+        let (wi, attenuation) = if self.distribution.is_smooth() {
+            (Vec3A::new(-wo.x, -wo.y, wo.z), schlick(wo.z, self.albedo))
+        } else {
+            let mut rng = rand::thread_rng();
+            let wm = self
+                .distribution
+                .sample_visible_normal(wo, (rng.gen(), rng.gen()));
+            let wi = reflect(wo, wm);
+            if wi.z <= 0.0 {
+                return Err(());
+            }
+
+            // Sampling visible normals leaves only the shadowing of the reflected direction
+            let shadowing = self.distribution.g(wo, wi) / self.distribution.g1(wo);
+
+            (wi, schlick(wo.dot(wm), self.albedo) * shadowing)
+        };
 
         let scattered = Ray {
-            direction: reflected + self.fuzz * random_in_unit_sphere(),
+            direction: frame.to_world(wi),
             origin: rec.p,
             time: ray.time,
         };
-        if scattered.direction.dot(rec.normal) > 0.0 {
-            Ok((self.albedo, scattered))
-        } else {
-            Err(())
-        }
+
+        Ok((attenuation, scattered))
     }
 }
 
+/// Glass, water and other clear materials, refracting or reflecting light by the Fresnel
+/// equations. Tinted glass is clear glass filled with an absorbing medium, so thick glass gets
+/// darker and more tinted than thin glass.
 #[derive(Debug)]
 pub struct Dialectric {
     pub ir: f32,
+    /// How the index of refraction varies with the wavelength in spectral rendering, `ir` is
+    /// used for all wavelengths without it.
+    pub dispersion: Option<Dispersion>,
+    /// Medium filling the glass, see `with_absorption`.
+    pub interior: Option<Arc<dyn Medium>>,
+}
+
+/// Index of refraction as a function of the wavelength in micrometres.
+#[derive(Clone, Copy, Debug)]
+pub enum Dispersion {
+    /// `n = a + b / λ²`, good enough for most glass over the visible range.
+    Cauchy { a: f32, b: f32 },
+    /// `n² = 1 + Σ b λ² / (λ² - c)`, as given for optical glasses, e.g. BK7 by
+    /// b = [1.0396, 0.2318, 1.0105] and c = [0.0060, 0.0200, 103.56].
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl Dispersion {
+    /// Index of refraction at a wavelength in nanometres.
+    pub fn ior(&self, lambda: f32) -> f32 {
+        let lambda = lambda / 1000.0;
+        let lambda2 = lambda * lambda;
+
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / lambda2,
+            Dispersion::Sellmeier { b, c } => (1.0
+                + (0..3)
+                    .map(|i| b[i] * lambda2 / (lambda2 - c[i]))
+                    .sum::<f32>())
+            .sqrt(),
+        }
+    }
 }
 
 impl Dialectric {
+    /// Creates perfectly clear glass.
+    pub fn new(ir: f32) -> Self {
+        Self {
+            ir,
+            dispersion: None,
+            interior: None,
+        }
+    }
+
+    /// Fills the glass with a medium absorbing light by the Beer-Lambert law, with the
+    /// coefficient `absorption` per channel.
+    pub fn with_absorption(mut self, absorption: Vec3A) -> Self {
+        self.interior = Some(Arc::new(HomogeneousMedium::absorbing(absorption)));
+        self
+    }
+
+    /// Fills the glass with a medium leaving `color` of the light after it travels `depth`
+    /// through it.
+    pub fn with_transmittance(self, color: Vec3A, depth: f32) -> Self {
+        let medium = HomogeneousMedium::with_transmittance(color, depth);
+
+        self.with_absorption(medium.sigma_a)
+    }
+
+    /// Makes the index of refraction depend on the wavelength, splitting white light into a
+    /// rainbow in spectral rendering. Rendering in RGB uses the index at the yellow helium
+    /// line of 587.6 nm, the one usually quoted for glass.
+    pub fn with_dispersion(mut self, dispersion: Dispersion) -> Self {
+        self.ir = dispersion.ior(587.6);
+        self.dispersion = Some(dispersion);
+        self
+    }
+
     fn reflectance(cosine: f32, ref_idx: f32) -> f32 {
         let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
         let r0 = r0 * r0;
@@ -74,13 +212,10 @@ impl Dialectric {
     }
 }
 
-impl Material for Dialectric {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Result<(Vec3A, Ray), ()> {
-        let refraction_ratio = if rec.front_face {
-            1.0 / self.ir
-        } else {
-            self.ir
-        };
+impl Dialectric {
+    /// Reflects or refracts the ray with the index of refraction `ir`.
+    fn scatter_with_ir(&self, ray: &Ray, rec: &HitRecord, ir: f32) -> (Vec3A, Ray) {
+        let refraction_ratio = if rec.front_face { 1.0 / ir } else { ir };
 
         let unit_direction = unit_vector(ray.direction);
 
@@ -103,8 +238,127 @@ impl Material for Dialectric {
             origin: rec.p,
             time: ray.time,
         };
-        {
-            Ok((Vec3A::new(1.0, 1.0, 1.0), scattered))
+
+        (Vec3A::new(1.0, 1.0, 1.0), scattered)
+    }
+}
+
+impl Material for Dialectric {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Result<(Vec3A, Ray), ()> {
+        Ok(self.scatter_with_ir(ray, rec, self.ir))
+    }
+
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        wavelengths: &mut SampledWavelengths,
+    ) -> Result<(Vec4, Ray), ()> {
+        // Every wavelength bends differently, so only the hero one can follow the path
+        let ir = match self.dispersion {
+            Some(dispersion) => {
+                wavelengths.terminate_secondary();
+                dispersion.ior(wavelengths.hero())
+            }
+            None => self.ir,
+        };
+
+        let (attenuation, scattered) = self.scatter_with_ir(ray, rec, ir);
+
+        Ok((wavelengths.uplift(attenuation), scattered))
+    }
+
+    fn interior(&self) -> Option<&Arc<dyn Medium>> {
+        self.interior.as_ref()
+    }
+}
+
+/// Frosted glass, reflecting and refracting off microfacets of the GGX distribution by the
+/// model of Walter et al. Each ray picks a facet it can see and then reflects or refracts by
+/// the Fresnel equations of that facet, so no light is lost to the choice. What is lost comes
+/// from facets shadowing each other, like for rough `Metal`.
+#[derive(Debug)]
+pub struct RoughDialectric {
+    pub ir: f32,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl RoughDialectric {
+    /// Creates glass of a `roughness` from 0 for clear glass to 1.
+    pub fn new(ir: f32, roughness: f32) -> Self {
+        Self {
+            ir,
+            distribution: TrowbridgeReitz::new(roughness, 0.0),
         }
     }
 }
+
+impl Material for RoughDialectric {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Result<(Vec3A, Ray), ()> {
+        if self.distribution.is_smooth() {
+            return Dialectric::new(self.ir).scatter(ray, rec);
+        }
+
+        // Index behind the surface over the one in front of it
+        let eta = if rec.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        };
+
+        let frame = Frame::around_y(rec.normal);
+        let wo = frame.to_local(-unit_vector(ray.direction));
+        if wo.z <= 0.0 {
+            return Err(());
+        }
+
+        let mut rng = rand::thread_rng();
+        let wm = self
+            .distribution
+            .sample_visible_normal(wo, (rng.gen(), rng.gen()));
+        let cos_theta = wo.dot(wm);
+
+        // Choosing by the reflectance cancels it from the weight of either choice
+        let wi = if rng.gen::<f32>() < fresnel_dielectric(cos_theta, eta) {
+            let wi = reflect(wo, wm);
+            if wi.z <= 0.0 {
+                return Err(());
+            }
+
+            wi
+        } else {
+            let wi = refract(-wo, wm, 1.0 / eta);
+            if wi.z >= 0.0 {
+                return Err(());
+            }
+
+            wi
+        };
+
+        let shadowing = self.distribution.g(wo, wi) / self.distribution.g1(wo);
+
+        let scattered = Ray {
+            direction: frame.to_world(wi),
+            origin: rec.p,
+            time: ray.time,
+        };
+
+        Ok((Vec3A::splat(shadowing), scattered))
+    }
+}
+
+/// Emits light from its texture without scattering, turning any surface into an area light.
+#[derive(Debug)]
+pub struct DiffuseLight {
+    pub emit: Box<dyn Texture + Send + Sync>,
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _ray: &Ray, _rec: &HitRecord) -> Result<(Vec3A, Ray), ()> {
+        Err(())
+    }
+
+    fn emitted(&self, u: f32, v: f32, p: Vec3A) -> Vec3A {
+        self.emit.value(u, v, p)
+    }
+}
@@ -0,0 +1,308 @@
+use std::{fmt::Debug, sync::Arc};
+
+use glam::{Vec2, Vec3A};
+use rand::Rng;
+
+use crate::{
+    aabb::AABB,
+    hittable::Hittable,
+    material::Material,
+    ray::{HitRecord, Ray},
+    texture::{color::SolidColor, Texture},
+    vec3::{random_unit_vector, unit_vector},
+};
+
+/// Fog, smoke or any other medium of constant density filling a closed boundary. Rays passing
+/// through it scatter at a distance sampled from the density, and the phase function material
+/// then picks the new direction. Boundaries that aren't convex are handled by walking all
+/// their hits, and rays starting inside the boundary scatter from where they start.
+///
+/// The distance is sampled anew on every call to `hit`, so the medium isn't a surface: `hit_all`
+/// strings together unrelated samples, and neither it nor `Csg` can tell inside from outside.
+/// Don't use it as a CSG operand, shape its boundary with CSG instead.
+pub struct ConstantMedium<H: Hittable> {
+    pub boundary: H,
+    neg_inv_density: f32,
+    pub phase_function: Arc<dyn Material>,
+}
+
+impl<H: Hittable> ConstantMedium<H> {
+    pub fn new(boundary: H, density: f32, phase_function: Arc<dyn Material>) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function,
+        }
+    }
+
+    /// Ranges of the ray parameter over which the ray is inside the boundary.
+    fn inside(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<(f32, f32)> {
+        let hits = self.boundary.hit_all(r, t_min, t_max);
+
+        // Starting out inside shows as leaving at the first hit
+        let mut entered = hits
+            .first()
+            .is_some_and(|rec| !rec.front_face)
+            .then_some(t_min);
+        let mut segments = vec![];
+
+        for rec in hits {
+            match (rec.front_face, entered) {
+                (true, None) => entered = Some(rec.t),
+                (false, Some(enter)) => {
+                    segments.push((enter, rec.t));
+                    entered = None;
+                }
+                _ => {}
+            }
+        }
+
+        // Boundaries that aren't closed leave the ray inside up to the end
+        if let Some(enter) = entered {
+            segments.push((enter, t_max));
+        }
+
+        segments
+    }
+}
+
+impl<H: Hittable> Hittable for ConstantMedium<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut rng = rand::thread_rng();
+        let ray_length = r.direction.length();
+
+        // The distance to scatter at has no memory, so every segment is sampled anew
+        for (enter, exit) in self.inside(r, t_min, t_max) {
+            let distance_inside_boundary = (exit - enter) * ray_length;
+            let hit_distance = self.neg_inv_density * rng.gen::<f32>().ln();
+
+            if hit_distance < distance_inside_boundary {
+                let t = enter + hit_distance / ray_length;
+
+                // The normal and side are arbitrary, the phase function ignores them
+                return Some(HitRecord {
+                    p: r.at(t),
+                    normal: Vec3A::X,
+                    t,
+                    u: 0.0,
+                    v: 0.0,
+                    barycentric: Vec2::ZERO,
+                    front_face: true,
+                    material: self.phase_function.clone(),
+                    media: None,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.boundary.bounding_box()
+    }
+}
+
+/// Phase function scattering equally in all directions.
+#[derive(Debug)]
+pub struct Isotropic {
+    pub albedo: Box<dyn Texture + Send + Sync>,
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Result<(Vec3A, Ray), ()> {
+        let scattered = Ray {
+            origin: rec.p,
+            direction: random_unit_vector(),
+            time: ray.time,
+        };
+
+        Ok((self.albedo.value(rec.u, rec.v, rec.p), scattered))
+    }
+}
+
+/// The Henyey-Greenstein phase function, scattering mostly forward for a positive asymmetry
+/// `g`, mostly backward for a negative one and equally in all directions for 0. Clouds are
+/// around 0.85.
+#[derive(Debug)]
+pub struct HenyeyGreenstein {
+    pub albedo: Box<dyn Texture + Send + Sync>,
+    pub g: f32,
+}
+
+impl HenyeyGreenstein {
+    /// Samples the cosine of the angle between the old and new direction.
+    pub fn sample_cos_theta(g: f32, xi: f32) -> f32 {
+        if g.abs() < 1e-3 {
+            return 1.0 - 2.0 * xi;
+        }
+
+        let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+        ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Result<(Vec3A, Ray), ()> {
+        let mut rng = rand::thread_rng();
+
+        let forward = unit_vector(ray.direction);
+        let (tangent, bitangent) = forward.any_orthonormal_pair();
+
+        let cos_theta = Self::sample_cos_theta(self.g, rng.gen());
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * std::f32::consts::PI * rng.gen::<f32>();
+
+        let direction =
+            cos_theta * forward + sin_theta * (phi.cos() * tangent + phi.sin() * bitangent);
+
+        let scattered = Ray {
+            origin: rec.p,
+            direction,
+            time: ray.time,
+        };
+
+        Ok((self.albedo.value(rec.u, rec.v, rec.p), scattered))
+    }
+}
+
+/// Medium filling space without a boundary, around the whole scene or on one side of the
+/// surfaces it is attached to with `WithMedia`. The integrator keeps track of the medium each
+/// ray travels through, up to the surface it hits. Rays leaving the scene aren't sampled, the
+/// background is seen through the medium as it is.
+pub trait Medium: Send + Sync + Debug {
+    /// Samples whether light traveling along the ray scatters in the medium before `t_max`.
+    fn sample(&self, r: &Ray, t_max: f32) -> MediumSample;
+}
+
+pub enum MediumSample {
+    /// The ray scatters by the phase function of the record, with `weight` applied to the
+    /// light coming from there.
+    Scatter { rec: HitRecord, weight: Vec3A },
+    /// The ray passes through to `t_max`, with `weight` applied to the light coming from there.
+    Pass { weight: Vec3A },
+}
+
+/// Medium of constant absorption and scattering coefficients per color channel, like colored
+/// glass, water or fog. Distances are sampled from the average scattering coefficient and
+/// weighted to account for the exact coefficients of every channel.
+#[derive(Debug)]
+pub struct HomogeneousMedium {
+    pub sigma_a: Vec3A,
+    pub sigma_s: Vec3A,
+    phase_function: Arc<dyn Material>,
+}
+
+impl HomogeneousMedium {
+    /// Creates a medium scattering by the Henyey-Greenstein phase function with asymmetry `g`.
+    pub fn new(sigma_a: Vec3A, sigma_s: Vec3A, g: f32) -> Self {
+        Self {
+            sigma_a,
+            sigma_s,
+            phase_function: Arc::new(HenyeyGreenstein {
+                albedo: Box::new(SolidColor::new(1.0, 1.0, 1.0)),
+                g,
+            }),
+        }
+    }
+
+    /// Medium absorbing without scattering, following the Beer-Lambert law.
+    pub fn absorbing(sigma_a: Vec3A) -> Self {
+        Self::new(sigma_a, Vec3A::ZERO, 0.0)
+    }
+
+    /// Absorbing medium leaving `color` of the light after it travels `depth` through it, like
+    /// the tint of glass of that thickness.
+    pub fn with_transmittance(color: Vec3A, depth: f32) -> Self {
+        assert!(depth > 0.0, "transmittance needs a positive depth");
+
+        let color = color.max(Vec3A::splat(f32::MIN_POSITIVE));
+        Self::absorbing(-Vec3A::new(color.x.ln(), color.y.ln(), color.z.ln()) / depth)
+    }
+}
+
+impl Medium for HomogeneousMedium {
+    fn sample(&self, r: &Ray, t_max: f32) -> MediumSample {
+        let ray_length = r.direction.length();
+        let sigma_t = self.sigma_a + self.sigma_s;
+        let sigma = self.sigma_s.dot(Vec3A::ONE) / 3.0;
+
+        if sigma <= 0.0 {
+            return MediumSample::Pass {
+                weight: (-sigma_t * t_max * ray_length).exp(),
+            };
+        }
+
+        let distance = -(1.0 - rand::thread_rng().gen::<f32>()).ln() / sigma;
+        let t = distance / ray_length;
+
+        if t < t_max {
+            // Transmittance and scattering coefficient over the probability of scattering here
+            let weight =
+                self.sigma_s * (-sigma_t * distance).exp() / (sigma * (-sigma * distance).exp());
+
+            MediumSample::Scatter {
+                rec: HitRecord {
+                    p: r.at(t),
+                    normal: Vec3A::X,
+                    t,
+                    u: 0.0,
+                    v: 0.0,
+                    barycentric: Vec2::ZERO,
+                    front_face: true,
+                    material: self.phase_function.clone(),
+                    media: None,
+                },
+                weight,
+            }
+        } else {
+            let distance = t_max * ray_length;
+
+            MediumSample::Pass {
+                weight: ((Vec3A::splat(sigma) - sigma_t) * distance).exp(),
+            }
+        }
+    }
+}
+
+/// Media on both sides of a surface, `None` standing for the medium of the scene.
+#[derive(Clone, Debug, Default)]
+pub struct MediumInterface {
+    pub interior: Option<Arc<dyn Medium>>,
+    pub exterior: Option<Arc<dyn Medium>>,
+}
+
+/// Attaches media to the inside and outside of an object, which rays enter when they are
+/// scattered through its surface, e.g. refracted into a glass sphere filled with a colored
+/// medium.
+pub struct WithMedia<H: Hittable> {
+    pub object: H,
+    pub interface: MediumInterface,
+}
+
+impl<H: Hittable> WithMedia<H> {
+    pub fn new(object: H, interface: MediumInterface) -> Self {
+        Self { object, interface }
+    }
+}
+
+impl<H: Hittable> Hittable for WithMedia<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut rec = self.object.hit(r, t_min, t_max)?;
+        rec.media = Some(self.interface.clone());
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.object.bounding_box()
+    }
+
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        let mut hits = self.object.hit_all(r, t_min, t_max);
+        for rec in &mut hits {
+            rec.media = Some(self.interface.clone());
+        }
+
+        hits
+    }
+}
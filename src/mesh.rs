@@ -0,0 +1,239 @@
+use std::sync::Arc;
+
+use glam::{Affine3A, Vec2, Vec3A};
+
+use crate::{
+    aabb::AABB,
+    bvh::sah::BVH,
+    geometry::intersect_triangle,
+    hittable::Hittable,
+    material::Material,
+    ray::{HitRecord, Ray},
+};
+
+/// Indexed triangle mesh. Vertex attributes are stored once and shared between the triangles
+/// referring to them, and the mesh keeps its own BVH over its triangles so it can be placed
+/// in a scene as a single `Hittable`.
+#[derive(Debug)]
+pub struct TriangleMesh {
+    pub positions: Vec<Vec3A>,
+    /// Per-vertex shading normals, empty if the mesh only has geometric normals.
+    pub normals: Vec<Vec3A>,
+    /// Per-vertex texture coordinates, empty if the mesh has none.
+    pub uvs: Vec<Vec2>,
+    pub indices: Vec<[u32; 3]>,
+    pub cull_backfaces: bool,
+    pub material: Arc<dyn Material>,
+    motion: Option<MeshMotion>,
+    bvh: BVH,
+    aabb: AABB,
+}
+
+/// Vertex positions of a deforming mesh sampled at evenly spaced times from `time0` to `time1`.
+#[derive(Debug)]
+struct MeshMotion {
+    samples: Vec<Vec<Vec3A>>,
+    time0: f32,
+    time1: f32,
+}
+
+impl MeshMotion {
+    fn position(&self, vertex: usize, time: f32) -> Vec3A {
+        let segments = self.samples.len() - 1;
+        let s = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0) * segments as f32;
+        let sample = (s as usize).min(segments - 1);
+
+        self.samples[sample][vertex].lerp(self.samples[sample + 1][vertex], s - sample as f32)
+    }
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Vec3A>,
+        normals: Vec<Vec3A>,
+        uvs: Vec<Vec2>,
+        indices: Vec<[u32; 3]>,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let (bvh, aabb) = Self::build_bvh(&[&positions], &indices);
+
+        Self {
+            bvh,
+            aabb,
+            positions,
+            normals,
+            uvs,
+            indices,
+            cull_backfaces: false,
+            material,
+            motion: None,
+        }
+    }
+
+    /// Turns the mesh into a deforming one, with vertices moving linearly between the position
+    /// samples, spaced evenly from `time0` to `time1`. Vertices are interpolated at the time of
+    /// each ray, so the deformation is motion blurred. Shading normals only describe the rest
+    /// pose, so deforming meshes use their geometric normals.
+    pub fn with_deformation(mut self, samples: Vec<Vec<Vec3A>>, time0: f32, time1: f32) -> Self {
+        assert!(samples.len() >= 2, "deformation needs at least two samples");
+        assert!(time0 < time1, "deformation needs time1 after time0");
+        assert!(
+            samples
+                .iter()
+                .all(|sample| sample.len() == self.positions.len()),
+            "deformation samples don't match the mesh vertices"
+        );
+
+        let sample_refs = samples
+            .iter()
+            .map(|sample| sample.as_slice())
+            .collect::<Vec<_>>();
+        (self.bvh, self.aabb) = Self::build_bvh(&sample_refs, &self.indices);
+
+        self.positions = samples[0].clone();
+        self.motion = Some(MeshMotion {
+            samples,
+            time0,
+            time1,
+        });
+
+        self
+    }
+
+    /// Builds the BVH over the triangles, bounding every triangle over all the position samples.
+    /// Vertices move linearly between samples, so those boxes hold at any time in between.
+    fn build_bvh(samples: &[&[Vec3A]], indices: &[[u32; 3]]) -> (BVH, AABB) {
+        let aabbs = indices
+            .iter()
+            .map(|triangle| {
+                samples.iter().fold(AABB::empty(), |aabb, positions| {
+                    aabb.join(&Self::aabb_of(positions, triangle))
+                })
+            })
+            .collect::<Vec<AABB>>();
+
+        let mut aabb = AABB::empty();
+        for triangle_aabb in &aabbs {
+            aabb.join_mut(triangle_aabb);
+        }
+
+        (BVH::build_from_aabbs(&aabbs), aabb)
+    }
+
+    /// Creates a mesh from a model loaded with `single_index`, placed by `transform`. Normals
+    /// are mapped by the inverse transpose, so they stay perpendicular to the surface under
+    /// rotations and non-uniform scales. A model without faces gives an empty mesh.
+    pub fn from_tobj(mesh: &tobj::Mesh, material: Arc<dyn Material>, transform: Affine3A) -> Self {
+        let normal_matrix = transform.matrix3.inverse().transpose();
+
+        let positions = mesh
+            .positions
+            .chunks(3)
+            .map(|i| transform.transform_point3a(Vec3A::new(i[0], i[1], i[2])))
+            .collect();
+        let normals = mesh
+            .normals
+            .chunks(3)
+            .map(|i| (normal_matrix * Vec3A::new(i[0], i[1], i[2])).normalize())
+            .collect();
+        let uvs = mesh
+            .texcoords
+            .chunks(2)
+            .map(|i| Vec2::new(i[0], i[1]))
+            .collect();
+        let indices = mesh.indices.chunks(3).map(|i| [i[0], i[1], i[2]]).collect();
+
+        Self::new(positions, normals, uvs, indices, material)
+    }
+
+    fn aabb_of(positions: &[Vec3A], triangle: &[u32; 3]) -> AABB {
+        let vertex0 = positions[triangle[0] as usize];
+        let vertex1 = positions[triangle[1] as usize];
+        let vertex2 = positions[triangle[2] as usize];
+
+        AABB::new(
+            vertex0.min(vertex1.min(vertex2)),
+            vertex0.max(vertex1.max(vertex2)),
+        )
+    }
+
+    /// Returns the vertices of a triangle at `time`.
+    pub fn vertices(&self, index: usize, time: f32) -> (Vec3A, Vec3A, Vec3A) {
+        let [i0, i1, i2] = self.indices[index].map(|i| i as usize);
+
+        match &self.motion {
+            Some(motion) => (
+                motion.position(i0, time),
+                motion.position(i1, time),
+                motion.position(i2, time),
+            ),
+            None => (self.positions[i0], self.positions[i1], self.positions[i2]),
+        }
+    }
+
+    /// Intersects a single triangle, returning the ray parameter and barycentric coordinates.
+    pub fn intersect_triangle(
+        &self,
+        index: usize,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<(f32, f32, f32)> {
+        let (vertex0, vertex1, vertex2) = self.vertices(index, r.time);
+
+        if self.cull_backfaces
+            && r.direction
+                .dot((vertex1 - vertex0).cross(vertex2 - vertex0))
+                >= 0.0
+        {
+            return None;
+        }
+
+        intersect_triangle(r, vertex0, vertex1, vertex2, t_min, t_max)
+    }
+
+    fn hit_record(&self, index: usize, r: &Ray, t: f32, u: f32, v: f32) -> HitRecord {
+        let [i0, i1, i2] = self.indices[index].map(|i| i as usize);
+        let (vertex0, vertex1, vertex2) = self.vertices(index, r.time);
+
+        let outward_normal = (vertex1 - vertex0).cross(vertex2 - vertex0).normalize();
+
+        let mut rec = HitRecord::new(r, t, outward_normal, u, v, &self.material);
+        rec.barycentric = Vec2::new(u, v);
+
+        if !self.normals.is_empty() && self.motion.is_none() {
+            let normal = rec
+                .interpolate([self.normals[i0], self.normals[i1], self.normals[i2]])
+                .normalize();
+            rec.normal = if rec.front_face { normal } else { -normal };
+        }
+
+        if !self.uvs.is_empty() {
+            let uv = rec.interpolate([self.uvs[i0], self.uvs[i1], self.uvs[i2]]);
+            (rec.u, rec.v) = (uv.x, uv.y);
+        }
+
+        rec
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut closest_hit_distance = t_max;
+        let mut closest_hit = None;
+
+        for index in self.bvh.traverse_indices(r) {
+            if let Some((t, u, v)) = self.intersect_triangle(index, r, t_min, closest_hit_distance)
+            {
+                closest_hit_distance = t;
+                closest_hit = Some((index, t, u, v));
+            }
+        }
+
+        closest_hit.map(|(index, t, u, v)| self.hit_record(index, r, t, u, v))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
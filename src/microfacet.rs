@@ -0,0 +1,152 @@
+use std::f32::consts::PI;
+
+use glam::Vec3A;
+
+/// Shading frame at a surface point, with the normal along z of the local coordinates.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame {
+    pub tangent: Vec3A,
+    pub bitangent: Vec3A,
+    pub normal: Vec3A,
+}
+
+impl Frame {
+    /// Frame around `normal` with the tangent running around the y axis, the way a lathe
+    /// brushes metal. Normals along y get an arbitrary tangent.
+    pub fn around_y(normal: Vec3A) -> Self {
+        let around = Vec3A::Y.cross(normal);
+        let tangent = if around.length_squared() > 1e-8 {
+            around.normalize()
+        } else {
+            normal.any_orthonormal_pair().0
+        };
+
+        Self {
+            tangent,
+            bitangent: normal.cross(tangent),
+            normal,
+        }
+    }
+
+    pub fn to_local(self, v: Vec3A) -> Vec3A {
+        Vec3A::new(
+            v.dot(self.tangent),
+            v.dot(self.bitangent),
+            v.dot(self.normal),
+        )
+    }
+
+    pub fn to_world(self, v: Vec3A) -> Vec3A {
+        v.x * self.tangent + v.y * self.bitangent + v.z * self.normal
+    }
+}
+
+/// The GGX or Trowbridge-Reitz distribution of microfacet normals, with the roughness
+/// `alpha_x` along the tangent and `alpha_y` along the bitangent. Directions are in the local
+/// shading frame, pointing away from the surface.
+#[derive(Clone, Copy, Debug)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: f32,
+    pub alpha_y: f32,
+}
+
+impl TrowbridgeReitz {
+    /// Surfaces smoother than this reflect and refract like perfect mirrors and glass, which
+    /// the distribution can't sample reliably.
+    const SMOOTH: f32 = 1e-3;
+
+    /// Distribution for a perceptual `roughness` from 0 to 1, stretched along the tangent by
+    /// an `anisotropy` from 0 to 1.
+    pub fn new(roughness: f32, anisotropy: f32) -> Self {
+        let alpha = roughness * roughness;
+        let aspect = (1.0 - 0.9 * anisotropy.clamp(0.0, 1.0)).sqrt();
+
+        Self {
+            alpha_x: alpha / aspect,
+            alpha_y: alpha * aspect,
+        }
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < Self::SMOOTH
+    }
+
+    /// The Smith auxiliary function, the hidden part of the microsurface seen from `w` relative
+    /// to the visible one.
+    pub fn lambda(&self, w: Vec3A) -> f32 {
+        let cos2 = w.z * w.z;
+        if cos2 == 0.0 {
+            return f32::INFINITY;
+        }
+
+        let tan2_alpha2 = ((w.x * self.alpha_x).powi(2) + (w.y * self.alpha_y).powi(2)) / cos2;
+
+        ((1.0 + tan2_alpha2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Part of the microfacets seen from `w` that aren't masked.
+    pub fn g1(&self, w: Vec3A) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Part of the microfacets seen from both `wo` and `wi`, masking and shadowing correlated.
+    pub fn g(&self, wo: Vec3A, wi: Vec3A) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Samples a microfacet normal visible from `w` by the method of Heitz, so only normals
+    /// that can actually be hit are drawn. `u` are two uniform random numbers.
+    pub fn sample_visible_normal(&self, w: Vec3A, u: (f32, f32)) -> Vec3A {
+        // Stretch to the hemisphere configuration of roughness 1
+        let wh = Vec3A::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z).normalize();
+        let wh = if wh.z < 0.0 { -wh } else { wh };
+
+        let t1 = if wh.z < 0.99999 {
+            Vec3A::Z.cross(wh).normalize()
+        } else {
+            Vec3A::X
+        };
+        let t2 = wh.cross(t1);
+
+        // Uniform point on the disk, warped to the part of the hemisphere facing w
+        let r = u.0.sqrt();
+        let phi = 2.0 * PI * u.1;
+        let p1 = r * phi.cos();
+        let p2 = r * phi.sin();
+        let s = 0.5 * (1.0 + wh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * p2;
+
+        let pz = (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+        let nh = p1 * t1 + p2 * t2 + pz * wh;
+
+        // Unstretch back to the actual roughness
+        Vec3A::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).normalize()
+    }
+}
+
+pub fn reflect(wo: Vec3A, n: Vec3A) -> Vec3A {
+    -wo + 2.0 * wo.dot(n) * n
+}
+
+/// Schlick's approximation of the Fresnel reflectance of a conductor with reflectance `f0` at
+/// normal incidence.
+pub fn schlick(cos_theta: f32, f0: Vec3A) -> Vec3A {
+    f0 + (Vec3A::ONE - f0) * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+/// Fresnel reflectance of unpolarized light arriving at `cos_theta` to the normal at the
+/// boundary of a dielectric, with `eta` the ratio of the index of refraction behind the
+/// boundary to the one in front. Light that can't refract is reflected entirely.
+pub fn fresnel_dielectric(cos_theta: f32, eta: f32) -> f32 {
+    let cos_i = cos_theta.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
@@ -0,0 +1,446 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    sync::Arc,
+};
+
+use glam::{Mat3A, Vec2, Vec3A};
+
+use crate::{
+    aabb::AABB,
+    geometry::intersect_triangle,
+    hittable::Hittable,
+    material::Material,
+    mesh::TriangleMesh,
+    ray::{HitRecord, Ray},
+};
+
+/// Bicubic Bézier patch over 4x4 control points, stored row by row. Rows run along `v` and
+/// columns along `u`, which are also the texture coordinates.
+///
+/// Patches can be intersected directly, by subdividing them until the pieces are flat and
+/// refining the hit on a piece with Newton's method, or be tessellated into a `TriangleMesh`.
+pub struct BezierPatch {
+    pub control_points: [Vec3A; 16],
+    pub material: Arc<dyn Material>,
+    aabb: AABB,
+}
+
+fn bernstein(t: f32) -> ([f32; 4], [f32; 4]) {
+    let s = 1.0 - t;
+
+    (
+        [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t],
+        [
+            -3.0 * s * s,
+            3.0 * s * s - 6.0 * t * s,
+            6.0 * t * s - 3.0 * t * t,
+            3.0 * t * t,
+        ],
+    )
+}
+
+/// Splits the cubic `p0..p3` in half with de Casteljau's algorithm.
+fn split_cubic(p: [Vec3A; 4]) -> ([Vec3A; 4], [Vec3A; 4]) {
+    let a = [
+        p[0].lerp(p[1], 0.5),
+        p[1].lerp(p[2], 0.5),
+        p[2].lerp(p[3], 0.5),
+    ];
+    let b = [a[0].lerp(a[1], 0.5), a[1].lerp(a[2], 0.5)];
+    let mid = b[0].lerp(b[1], 0.5);
+
+    ([p[0], a[0], b[0], mid], [mid, b[1], a[2], p[3]])
+}
+
+fn aabb_of(points: &[Vec3A]) -> AABB {
+    points
+        .iter()
+        .fold(AABB::empty(), |aabb, point| aabb.grow(point))
+}
+
+/// Splits control points in half along `u` if `along_u`, otherwise along `v`.
+fn split_patch(cp: &[Vec3A; 16], along_u: bool) -> ([Vec3A; 16], [Vec3A; 16]) {
+    let mut first = [Vec3A::ZERO; 16];
+    let mut second = [Vec3A::ZERO; 16];
+
+    for line in 0..4 {
+        let index = |i: usize| {
+            if along_u {
+                line * 4 + i
+            } else {
+                i * 4 + line
+            }
+        };
+
+        let (a, b) = split_cubic([cp[index(0)], cp[index(1)], cp[index(2)], cp[index(3)]]);
+        for i in 0..4 {
+            first[index(i)] = a[i];
+            second[index(i)] = b[i];
+        }
+    }
+
+    (first, second)
+}
+
+/// Largest distance of a control point from the bilinear patch through the corners.
+fn flatness(cp: &[Vec3A; 16]) -> f32 {
+    let mut deviation: f32 = 0.0;
+
+    for row in 0..4 {
+        for column in 0..4 {
+            let u = column as f32 / 3.0;
+            let v = row as f32 / 3.0;
+            let bilinear = cp[0].lerp(cp[3], u).lerp(cp[12].lerp(cp[15], u), v);
+
+            deviation = deviation.max((cp[row * 4 + column] - bilinear).length());
+        }
+    }
+
+    deviation
+}
+
+impl BezierPatch {
+    /// Subdivisions after which a piece is intersected as flat regardless of its shape.
+    const MAX_DEPTH: u32 = 8;
+    /// Deviation from flat, relative to the size of a piece, below which it isn't subdivided.
+    const FLATNESS: f32 = 0.01;
+    const NEWTON_ITERATIONS: usize = 8;
+
+    pub fn new(control_points: [Vec3A; 16], material: Arc<dyn Material>) -> Self {
+        Self {
+            aabb: aabb_of(&control_points),
+            control_points,
+            material,
+        }
+    }
+
+    /// Returns the point at `(u, v)` and the partial derivatives along `u` and `v`.
+    pub fn evaluate(&self, u: f32, v: f32) -> (Vec3A, Vec3A, Vec3A) {
+        let (bu, dbu) = bernstein(u);
+        let (bv, dbv) = bernstein(v);
+
+        let mut p = Vec3A::ZERO;
+        let mut dpdu = Vec3A::ZERO;
+        let mut dpdv = Vec3A::ZERO;
+
+        for row in 0..4 {
+            for column in 0..4 {
+                let cp = self.control_points[row * 4 + column];
+                p += bu[column] * bv[row] * cp;
+                dpdu += dbu[column] * bv[row] * cp;
+                dpdv += bu[column] * dbv[row] * cp;
+            }
+        }
+
+        (p, dpdu, dpdv)
+    }
+
+    /// Normal at `(u, v)`. Patches with edges collapsed to a point, like the top of the teapot
+    /// lid, have no normal there, it is taken slightly towards the middle of the patch instead.
+    pub fn normal(&self, u: f32, v: f32) -> Vec3A {
+        let (_, dpdu, dpdv) = self.evaluate(u, v);
+
+        match dpdu.cross(dpdv).try_normalize() {
+            Some(normal) => normal,
+            None => {
+                let (_, dpdu, dpdv) = self.evaluate(u + (0.5 - u) * 1e-3, v + (0.5 - v) * 1e-3);
+                dpdu.cross(dpdv).normalize_or_zero()
+            }
+        }
+    }
+
+    /// Tessellates the patch into a grid of `rate` by `rate` quads, each split into two
+    /// triangles, with the normals and texture coordinates of the patch.
+    pub fn tessellate(&self, rate: usize) -> TriangleMesh {
+        tessellate_patches(std::slice::from_ref(self), rate, self.material.clone())
+    }
+
+    /// Refines a hit near `(u, v, t)` with Newton's method on the distance between the ray and
+    /// the patch.
+    fn refine(&self, r: &Ray, mut u: f32, mut v: f32, mut t: f32) -> Option<(f32, f32, f32)> {
+        let tolerance = 1e-5 * self.aabb.size().max_element().max(1.0);
+
+        for _ in 0..Self::NEWTON_ITERATIONS {
+            let (p, dpdu, dpdv) = self.evaluate(u, v);
+            let residual = p - r.at(t);
+
+            if residual.length() < tolerance {
+                return Some((u, v, t));
+            }
+
+            let jacobian = Mat3A::from_cols(dpdu, dpdv, -r.direction);
+            if jacobian.determinant().abs() < 1e-12 {
+                return None;
+            }
+
+            let step = jacobian.inverse() * residual;
+            u -= step.x;
+            v -= step.y;
+            t -= step.z;
+        }
+
+        let (p, _, _) = self.evaluate(u, v);
+        ((p - r.at(t)).length() < tolerance).then_some((u, v, t))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn intersect_piece(
+        &self,
+        cp: &[Vec3A; 16],
+        u_range: Vec2,
+        v_range: Vec2,
+        depth: u32,
+        r: &Ray,
+        t_min: f32,
+        closest: &mut Option<(f32, f32, f32)>,
+        t_max: f32,
+    ) {
+        let t_max = closest.map_or(t_max, |(_, _, t)| t);
+
+        let aabb = aabb_of(cp);
+        match r.aabb_interval(aabb) {
+            Some((enter, exit)) if exit >= t_min && enter <= t_max => {}
+            _ => return,
+        }
+
+        let size = aabb.size().max_element();
+        if depth > 0 && flatness(cp) > Self::FLATNESS * size {
+            // Halve the piece along its longer direction
+            let along_u = (cp[3] - cp[0]).length() + (cp[15] - cp[12]).length()
+                >= (cp[12] - cp[0]).length() + (cp[15] - cp[3]).length();
+            let (first, second) = split_patch(cp, along_u);
+
+            let (first_u, second_u, first_v, second_v) = if along_u {
+                let mid = 0.5 * (u_range.x + u_range.y);
+                (
+                    Vec2::new(u_range.x, mid),
+                    Vec2::new(mid, u_range.y),
+                    v_range,
+                    v_range,
+                )
+            } else {
+                let mid = 0.5 * (v_range.x + v_range.y);
+                (
+                    u_range,
+                    u_range,
+                    Vec2::new(v_range.x, mid),
+                    Vec2::new(mid, v_range.y),
+                )
+            };
+
+            self.intersect_piece(
+                &first,
+                first_u,
+                first_v,
+                depth - 1,
+                r,
+                t_min,
+                closest,
+                t_max,
+            );
+            self.intersect_piece(
+                &second,
+                second_u,
+                second_v,
+                depth - 1,
+                r,
+                t_min,
+                closest,
+                t_max,
+            );
+            return;
+        }
+
+        // Start from the hit on the two triangles between the corners of the flat piece
+        let corners = [cp[0], cp[3], cp[15], cp[12]];
+        let corner_uvs = [
+            Vec2::new(u_range.x, v_range.x),
+            Vec2::new(u_range.y, v_range.x),
+            Vec2::new(u_range.y, v_range.y),
+            Vec2::new(u_range.x, v_range.y),
+        ];
+        let guess = [(0, 1, 2), (0, 2, 3)].iter().find_map(|&(a, b, c)| {
+            // Generous t range, the corners only approximate the surface
+            let (t, b1, b2) =
+                intersect_triangle(r, corners[a], corners[b], corners[c], f32::MIN, f32::MAX)?;
+            let uv = (1.0 - b1 - b2) * corner_uvs[a] + b1 * corner_uvs[b] + b2 * corner_uvs[c];
+            Some((uv, t))
+        });
+        let (uv, t) = match guess {
+            Some(guess) => guess,
+            // Near the silhouette, start at the middle of the piece instead
+            None => (
+                Vec2::new(0.5 * (u_range.x + u_range.y), 0.5 * (v_range.x + v_range.y)),
+                (aabb.center() - r.origin).dot(r.direction) / r.direction.length_squared(),
+            ),
+        };
+
+        if let Some((u, v, t)) = self.refine(r, uv.x, uv.y, t) {
+            // Hits wandering off the piece are found by the piece they belong to
+            let margin = 1e-3;
+            if t >= t_min
+                && t < t_max
+                && (u_range.x - margin..=u_range.y + margin).contains(&u)
+                && (v_range.x - margin..=v_range.y + margin).contains(&v)
+                && (0.0..=1.0).contains(&u)
+                && (0.0..=1.0).contains(&v)
+            {
+                *closest = Some((u, v, t));
+            }
+        }
+    }
+}
+
+impl Hittable for BezierPatch {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut closest = None;
+
+        self.intersect_piece(
+            &self.control_points,
+            Vec2::new(0.0, 1.0),
+            Vec2::new(0.0, 1.0),
+            Self::MAX_DEPTH,
+            r,
+            t_min,
+            &mut closest,
+            t_max,
+        );
+
+        let (u, v, t) = closest?;
+
+        Some(HitRecord::new(
+            r,
+            t,
+            self.normal(u, v),
+            u,
+            v,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
+
+/// Tessellates all `patches` into a single mesh, every patch into `rate` by `rate` quads.
+pub fn tessellate_patches(
+    patches: &[BezierPatch],
+    rate: usize,
+    material: Arc<dyn Material>,
+) -> TriangleMesh {
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    let mut indices = vec![];
+
+    for patch in patches {
+        let base = positions.len() as u32;
+
+        for row in 0..=rate {
+            for column in 0..=rate {
+                let u = column as f32 / rate as f32;
+                let v = row as f32 / rate as f32;
+                let (p, _, _) = patch.evaluate(u, v);
+
+                positions.push(p);
+                normals.push(patch.normal(u, v));
+                uvs.push(Vec2::new(u, v));
+            }
+        }
+
+        let vertex = |row: usize, column: usize| base + (row * (rate + 1) + column) as u32;
+        for row in 0..rate {
+            for column in 0..rate {
+                let (v00, v01) = (vertex(row, column), vertex(row, column + 1));
+                let (v10, v11) = (vertex(row + 1, column), vertex(row + 1, column + 1));
+
+                indices.push([v00, v01, v11]);
+                indices.push([v00, v11, v10]);
+            }
+        }
+    }
+
+    // Collapsed patch edges give triangles without area, which can't be hit anyway
+    let indices = indices
+        .into_iter()
+        .filter(|[a, b, c]| {
+            let (a, b, c) = (
+                positions[*a as usize],
+                positions[*b as usize],
+                positions[*c as usize],
+            );
+            (b - a).cross(c - a).length_squared() > 0.0
+        })
+        .collect();
+
+    TriangleMesh::new(positions, normals, uvs, indices, material)
+}
+
+/// Loads patches from a text file in the format of Newell's teapot data: the number of
+/// patches, a line of 16 one-based control point indices per patch, the number of vertices
+/// and a line of coordinates per vertex. Values are separated by commas or whitespace. Every
+/// vertex is mapped through `transform`, e.g. to turn the z-up teapot upright.
+pub fn load_patches(
+    path: &str,
+    material: Arc<dyn Material>,
+    transform: impl Fn(Vec3A) -> Vec3A,
+) -> Result<Vec<BezierPatch>> {
+    let invalid =
+        |message: String| Error::new(ErrorKind::InvalidData, format!("{}: {}", path, message));
+
+    let contents = fs::read_to_string(path)?;
+    let mut lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+
+    let mut next_values = |expected: usize| -> Result<Vec<f32>> {
+        let line = lines
+            .next()
+            .ok_or_else(|| invalid("unexpected end of file".to_string()))?;
+
+        let values = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse::<f32>())
+            .collect::<std::result::Result<Vec<f32>, _>>()
+            .map_err(|err| invalid(format!("{}: {}", line, err)))?;
+
+        if values.len() != expected {
+            return Err(invalid(format!(
+                "expected {} values, found {} in '{}'",
+                expected,
+                values.len(),
+                line
+            )));
+        }
+
+        Ok(values)
+    };
+
+    let patch_count = next_values(1)?[0] as usize;
+    let patch_indices = (0..patch_count)
+        .map(|_| next_values(16))
+        .collect::<Result<Vec<_>>>()?;
+
+    let vertex_count = next_values(1)?[0] as usize;
+    let vertices = (0..vertex_count)
+        .map(|_| next_values(3).map(|v| transform(Vec3A::new(v[0], v[1], v[2]))))
+        .collect::<Result<Vec<_>>>()?;
+
+    patch_indices
+        .into_iter()
+        .map(|indices| {
+            let mut control_points = [Vec3A::ZERO; 16];
+            for (control_point, index) in control_points.iter_mut().zip(indices) {
+                *control_point = *vertices
+                    .get((index as usize).wrapping_sub(1))
+                    .ok_or_else(|| invalid(format!("vertex {} out of range", index)))?;
+            }
+
+            Ok(BezierPatch::new(control_points, material.clone()))
+        })
+        .collect()
+}
@@ -1,8 +1,11 @@
-use std::sync::Arc;
+use std::{
+    ops::{Add, Mul},
+    sync::Arc,
+};
 
-use glam::Vec3A;
+use glam::{Vec2, Vec3A};
 
-use crate::{aabb::AABB, hittable::Hittable, material::Material};
+use crate::{aabb::AABB, hittable::Hittable, material::Material, medium::MediumInterface};
 
 #[derive(Clone)]
 pub struct HitRecord {
@@ -11,8 +14,53 @@ pub struct HitRecord {
     pub t: f32,
     pub u: f32,
     pub v: f32,
+    /// Barycentric coordinates of the hit on triangles, `u`/`v` hold the texture coordinates.
+    pub barycentric: Vec2,
     pub front_face: bool,
     pub material: Arc<dyn Material>,
+    /// Media on both sides of the surface, if they differ from the medium of the scene.
+    pub media: Option<MediumInterface>,
+}
+
+impl HitRecord {
+    /// Creates the record of a hit at `t`, orienting the normal against the ray.
+    pub fn new(
+        r: &Ray,
+        t: f32,
+        outward_normal: Vec3A,
+        u: f32,
+        v: f32,
+        material: &Arc<dyn Material>,
+    ) -> Self {
+        let front_face = r.direction.dot(outward_normal) < 0.0;
+
+        Self {
+            p: r.at(t),
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            t,
+            u,
+            v,
+            barycentric: Vec2::ZERO,
+            front_face,
+            material: material.clone(),
+            media: None,
+        }
+    }
+
+    /// Interpolates values given at the three corners of a triangle, like texture coordinates
+    /// or shading normals, at the barycentric coordinates of the hit.
+    pub fn interpolate<T>(&self, [value0, value1, value2]: [T; 3]) -> T
+    where
+        T: Add<Output = T> + Mul<f32, Output = T>,
+    {
+        let Vec2 { x: u, y: v } = self.barycentric;
+
+        value0 * (1.0 - u - v) + value1 * u + value2 * v
+    }
 }
 
 pub struct Ray {
@@ -46,6 +94,11 @@ impl Ray {
     }
 
     pub fn aabb_intersect(&self, aabb: AABB) -> bool {
+        self.aabb_interval(aabb).is_some()
+    }
+
+    /// Returns the range of the ray parameter over which the ray is inside the box.
+    pub fn aabb_interval(&self, aabb: AABB) -> Option<(f32, f32)> {
         let inv_d = 1.0 / self.direction;
 
         let ray_min = (aabb.minimum - self.origin) * inv_d;
@@ -57,6 +110,7 @@ impl Ray {
         let tmin = tsmaller.x.max(tsmaller.y.max(tsmaller.z));
         let tmax = tbigger.x.min(tbigger.y.min(tbigger.z));
 
-        tmin < tmax
+        // Flat boxes, e.g. of axis-aligned quads, are hit with tmin == tmax
+        (tmin <= tmax).then_some((tmin, tmax))
     }
 }
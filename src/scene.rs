@@ -1,19 +1,45 @@
-use std::{fs::File, io::BufReader, sync::Arc};
+use std::{f32::consts::PI, fs::File, io::BufReader, path::Path, sync::Arc};
 
-use glam::{Vec3, Vec3A};
+use glam::{Affine3A, Quat, Vec2, Vec3, Vec3A};
 use rand::{distributions::Alphanumeric, rngs::SmallRng, thread_rng, Rng, SeedableRng};
 use rand_seeder::Seeder;
-use tobj::{LoadOptions, GPU_LOAD_OPTIONS, OFFLINE_RENDERING_LOAD_OPTIONS};
+use tobj::GPU_LOAD_OPTIONS;
 
 use crate::{
     aabb::AABB,
-    geometry::{Sphere, Triangle},
+    animation::{AnimatedObject, Animation, CameraAnimation, Interpolation, Keyframe, Track},
+    axis::Axis,
+    bvh::sah::BVHAggregate,
+    csg::{Csg, CsgOp},
+    curve::{load_curves, Curve, CurveType},
+    displacement::displace,
+    geometry::{
+        AxisRect, Cone, Cuboid, Cylinder, Disk, MovingSphere, Paraboloid, Plane, Quad, Sphere,
+        Torus, Triangle,
+    },
+    heightfield::Heightfield,
     hittable::Hittable,
-    material::{Dialectric, Lambertian, Metal},
+    instance::{AnimatedInstance, TransformKeyframe},
+    material::{
+        Dialectric, DiffuseLight, Dispersion, Lambertian, Material, Metal, RoughDialectric,
+    },
+    medium::{
+        ConstantMedium, HenyeyGreenstein, HomogeneousMedium, Isotropic, Medium, MediumInterface,
+        WithMedia,
+    },
+    mesh::TriangleMesh,
+    patch::{load_patches, tessellate_patches, BezierPatch},
+    sdf::{
+        Intersection as SdfIntersection, Mandelbulb, RoundBox, SdfObject, SdfSphere, SdfTorus,
+        SmoothUnion, Subtraction, Union as SdfUnion,
+    },
+    subdivision::{PolyMesh, SUBDIVISION_LOAD_OPTIONS},
     texture::{
-        color::{NoiseTexture, SolidColor},
+        color::{CheckerTexture, NoiseTexture, SolidColor},
         image::ImageTexture,
     },
+    transform::{Rotate, Scale, Translate},
+    volume::{HeterogeneousMedium, VoxelGrid},
 };
 
 pub fn get_seed(length: usize) -> String {
@@ -27,22 +53,44 @@ pub fn get_seed(length: usize) -> String {
 
 pub struct Scene {
     pub objects: Vec<Box<dyn Hittable>>,
+    /// Objects without a finite bounding box, e.g. a `Plane`, kept out of the BVH and tested
+    /// against every ray.
+    pub unbounded: Vec<Box<dyn Hittable>>,
+    /// Color of rays leaving the scene, a sky gradient if unset.
+    pub background: Option<Vec3A>,
+    /// Medium filling the scene, like fog or water, which the camera is in as well.
+    pub medium: Option<Arc<dyn Medium>>,
 }
 
 impl Scene {
     #[allow(dead_code)]
     pub fn new() -> Scene {
-        Scene { objects: vec![] }
+        Scene {
+            objects: vec![],
+            unbounded: vec![],
+            background: None,
+            medium: None,
+        }
     }
 
+    /// Replaces the objects of the scene. Those without a finite bounding box are added to
+    /// `unbounded` instead, so they stay out of the BVH.
     #[allow(dead_code)]
     pub fn set(&mut self, objects: Vec<Box<dyn Hittable>>) {
-        self.objects = objects;
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects.into_iter().partition(|object| {
+            let aabb = object.bounding_box();
+            aabb.minimum.is_finite() && aabb.maximum.is_finite()
+        });
+
+        self.objects = bounded;
+        self.unbounded.extend(unbounded);
     }
 
     #[allow(dead_code)]
     pub fn clear(&mut self) {
-        self.objects.clear()
+        self.objects.clear();
+        self.unbounded.clear();
+        self.medium = None;
     }
 
     #[allow(dead_code)]
@@ -68,18 +116,15 @@ impl Scene {
 
     pub fn from_obj(path: String) -> Self {
         let (models, materials) =
-            tobj::load_obj(path, &LoadOptions::default()).expect("Failed to load obj file");
+            tobj::load_obj(path, &GPU_LOAD_OPTIONS).expect("Failed to load obj file");
 
         let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
 
         let ground_material = Arc::new(Lambertian {
             albedo: Box::new(NoiseTexture::new()),
         });
-        objects.push(Box::new(Sphere {
-            position: Vec3A::new(0.0, -1000.0, 0.0),
-            radius: 1000.0,
-            material: ground_material,
-        }));
+        let ground: Box<dyn Hittable> =
+            Box::new(Plane::new(Vec3A::ZERO, Vec3A::Y, ground_material));
 
         let mut totsize: usize = 0;
 
@@ -94,30 +139,93 @@ impl Scene {
 
             totsize += mesh.indices.len() / 3;
 
-            let positions: Vec<Vec3A> = mesh
-                .positions
-                .chunks(3)
-                .map(|i| Vec3A::new(i[0], i[1], i[2]))
-                .collect();
+            let material = Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(1.0, 0.0, 0.0)),
+            });
 
-            mesh.indices
-                .chunks(3)
-                .map(|i| {
-                    objects.push(Box::new(Triangle {
-                        material: Arc::new(Lambertian {
-                            albedo: Box::new(SolidColor::new(1.0, 0.0, 0.0)),
-                        }),
-                        vertex0: positions[i[0] as usize],
-                        vertex1: positions[i[1] as usize],
-                        vertex2: positions[i[2] as usize],
-                    }))
-                })
-                .for_each(drop);
+            objects.push(Box::new(TriangleMesh::from_tobj(
+                &mesh,
+                material,
+                Affine3A::IDENTITY,
+            )));
         }
 
         println!("{} triangles", totsize);
 
-        Self { objects }
+        Self {
+            objects,
+            unbounded: vec![ground],
+            background: None,
+            medium: None,
+        }
+    }
+
+    /// Loads an obj file like `from_obj`, but with the diffuse colors and textures of its
+    /// materials, and displaces the models whose material has a `map_Disp` texture. Those are
+    /// tessellated down to `edge_length` and moved along their normals by up to `scale`.
+    #[allow(dead_code)]
+    pub fn from_obj_displaced(path: &str, scale: f32, edge_length: f32) -> Self {
+        let (models, materials) =
+            tobj::load_obj(path, &GPU_LOAD_OPTIONS).expect("Failed to load obj file");
+        let materials = materials.expect("Failed to load MTL file");
+
+        // Textures are referenced relative to the obj file
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+        let texture_path = |name: &str| directory.join(name).to_string_lossy().into_owned();
+
+        let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
+        let mut totsize: usize = 0;
+
+        for model in models {
+            let mtl = model.mesh.material_id.map(|id| &materials[id]);
+
+            let material: Arc<dyn Material> = match mtl {
+                Some(mtl) if !mtl.diffuse_texture.is_empty() => Arc::new(Lambertian {
+                    albedo: Box::new(ImageTexture::new(texture_path(&mtl.diffuse_texture))),
+                }),
+                Some(mtl) => Arc::new(Lambertian {
+                    albedo: Box::new(SolidColor::new(
+                        mtl.diffuse[0],
+                        mtl.diffuse[1],
+                        mtl.diffuse[2],
+                    )),
+                }),
+                None => Arc::new(Lambertian {
+                    albedo: Box::new(SolidColor::new(0.8, 0.8, 0.8)),
+                }),
+            };
+
+            let mut mesh = TriangleMesh::from_tobj(&model.mesh, material, Affine3A::IDENTITY);
+
+            // tobj keeps the parameters it doesn't know, the file name comes after any options
+            let displacement = mtl
+                .and_then(|mtl| mtl.unknown_param.get("map_Disp"))
+                .and_then(|param| param.split_whitespace().last());
+            if let Some(name) = displacement {
+                let texture = ImageTexture::new(texture_path(name));
+                mesh = displace(&mesh, &texture, scale, edge_length);
+            }
+
+            totsize += mesh.indices.len();
+            objects.push(Box::new(mesh));
+        }
+
+        println!("{} triangles", totsize);
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        Self {
+            objects,
+            unbounded: vec![ground],
+            background: None,
+            medium: None,
+        }
     }
 
     pub fn randomize(&mut self) -> &mut Self {
@@ -127,11 +235,8 @@ impl Scene {
 
         let mut objects: Vec<Box<dyn Hittable>> = vec![];
 
-        objects.push(Box::new(Sphere {
-            position: Vec3A::new(0.0, -1000.0, 0.0),
-            radius: 1000.0,
-            material: ground_material,
-        }));
+        let ground: Box<dyn Hittable> =
+            Box::new(Plane::new(Vec3A::ZERO, Vec3A::Y, ground_material));
 
         let seed = "D4en7gYSdsaaOzPd58BfTa79ugWvcEm5"; //get_seed(32);
 
@@ -155,12 +260,12 @@ impl Scene {
                         let albedo = Vec3A::new(rng.gen(), rng.gen(), rng.gen())
                             * Vec3A::new(rng.gen(), rng.gen(), rng.gen());
                         let texture = Box::new(SolidColor::new(albedo.x, albedo.y, albedo.z));
-                        objects.push(Box::new(Triangle {
-                            material: Arc::new(Lambertian { albedo: texture }),
-                            vertex0: center + Vec3A::new(-0.2, 0.0, 0.2),
-                            vertex1: center + Vec3A::new(0.0, 0.0, 0.0),
-                            vertex2: center + Vec3A::new(0.0, 0.2, 0.2),
-                        }));
+                        objects.push(Box::new(Triangle::new(
+                            center + Vec3A::new(-0.2, 0.0, 0.2),
+                            center + Vec3A::new(0.0, 0.0, 0.0),
+                            center + Vec3A::new(0.0, 0.2, 0.2),
+                            Arc::new(Lambertian { albedo: texture }),
+                        )));
                     } else if choose_mat < 0.95 {
                         // metal
                         let albedo = Vec3A::new(
@@ -172,14 +277,14 @@ impl Scene {
                         objects.push(Box::new(Sphere {
                             position: center,
                             radius: 0.2,
-                            material: Arc::new(Metal { albedo, fuzz }),
+                            material: Arc::new(Metal::new(albedo, fuzz)),
                         }));
                     } else {
                         // glass
                         objects.push(Box::new(Sphere {
                             position: center,
                             radius: 0.2,
-                            material: Arc::new(Dialectric { ir: 1.5 }),
+                            material: Arc::new(Dialectric::new(1.5)),
                         }));
                     }
                 }
@@ -192,7 +297,7 @@ impl Scene {
             material: Arc::new(Lambertian {
                 albedo: Box::new(ImageTexture::new("earthmap.jpg".into())),
             }),
-            //material: Arc::new(Dialectric { ir: 1.5 }),
+            //material: Arc::new(Dialectric::new(1.5)),
         }));
         objects.push(Box::new(Sphere {
             position: Vec3A::new(-4.0, 1.0, 0.0),
@@ -204,52 +309,15 @@ impl Scene {
         objects.push(Box::new(Sphere {
             position: Vec3A::new(4.0, 1.0, 0.0),
             radius: 1.0,
-            material: Arc::new(Metal {
-                albedo: Vec3A::new(0.7, 0.6, 0.5),
-                fuzz: 0.0,
-            }),
+            material: Arc::new(Metal::new(Vec3A::new(0.7, 0.6, 0.5), 0.0)),
         }));
 
         self.objects = objects;
+        self.unbounded = vec![ground];
 
         self
     }
 
-    pub fn plane() -> Vec<Box<dyn Hittable>> {
-        let (models, materials) =
-            tobj::load_obj("plane.obj", &LoadOptions::default()).expect("Failed to load obj file");
-
-        let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
-
-        //let materials = materials.expect("Failed to load MTL file");
-
-        for model in models {
-            let mesh = model.mesh;
-
-            let positions: Vec<Vec3A> = mesh
-                .positions
-                .chunks(3)
-                .map(|i| Vec3A::new(i[0], i[1], i[2]) + Vec3A::new(0.9, 0.2, 0.9))
-                .collect();
-
-            mesh.indices
-                .chunks(3)
-                .map(|i| {
-                    objects.push(Box::new(Triangle {
-                        material: Arc::new(Lambertian {
-                            albedo: Box::new(SolidColor::new(1.0, 0.0, 0.0)),
-                        }),
-                        vertex0: positions[i[0] as usize] * 1000.0,
-                        vertex1: positions[i[1] as usize] * 1000.0,
-                        vertex2: positions[i[2] as usize] * 1000.0,
-                    }))
-                })
-                .for_each(drop);
-        }
-
-        objects
-    }
-
     pub fn randomize_bunnies(&mut self) -> &mut Self {
         let ground_material = Arc::new(Lambertian {
             albedo: Box::new(NoiseTexture::new()),
@@ -257,19 +325,37 @@ impl Scene {
 
         let mut objects: Vec<Box<dyn Hittable>> = vec![];
 
-        objects.push(Box::new(Sphere {
-            position: Vec3A::new(0.0, -1000.0, 0.0),
-            radius: 1000.0,
-            material: ground_material,
-        }));
+        let ground: Box<dyn Hittable> =
+            Box::new(Plane::new(Vec3A::ZERO, Vec3A::Y, ground_material));
 
         let (models, materials) =
-            tobj::load_obj("bunny.obj", &LoadOptions::default()).expect("Failed to load obj file");
-
-        objects.append(&mut Self::plane());
+            tobj::load_obj("bunny.obj", &GPU_LOAD_OPTIONS).expect("Failed to load obj file");
 
         let materials = materials.expect("Failed to load MTL file");
 
+        // Every bunny is an instance of the same meshes, so they are only loaded once
+        let bunnies: Vec<Arc<dyn Hittable>> = models
+            .iter()
+            .map(|model| {
+                let mesh = &model.mesh;
+                let material = &materials[mesh.material_id.unwrap()];
+
+                let texture = &material.diffuse_texture;
+
+                let bunny_material = Arc::new(Lambertian {
+                    albedo: Box::new(ImageTexture::new(texture.to_string())),
+                });
+
+                Arc::new(TriangleMesh::from_tobj(
+                    mesh,
+                    bunny_material,
+                    Affine3A::IDENTITY,
+                )) as Arc<dyn Hittable>
+            })
+            .collect();
+
+        let bunny_scale = 0.005;
+
         let seed = "D4en7gYSdsaaOzPd58BfTa79ugWvcEm5"; //get_seed(32);
 
         //let mut rng: SmallRng = Seeder::from(seed.clone()).make_rng();
@@ -291,33 +377,11 @@ impl Scene {
                         // diffuse
                         let albedo = Vec3A::new(rng.gen(), rng.gen(), rng.gen())
                             * Vec3A::new(rng.gen(), rng.gen(), rng.gen());
-                        for model in &models {
-                            let mesh = &model.mesh;
-                            let material = &materials[mesh.material_id.unwrap()];
-
-                            let texture = &material.diffuse_texture;
-
-                            let bunny_material = Arc::new(Lambertian {
-                                albedo: Box::new(ImageTexture::new(texture.to_string())),
-                            });
-
-                            let positions: Vec<Vec3A> = mesh
-                                .positions
-                                .chunks(3)
-                                .map(|i| Vec3A::new(i[0], i[1], i[2]))
-                                .collect();
-
-                            mesh.indices
-                                .chunks(3)
-                                .map(|i| {
-                                    objects.push(Box::new(Triangle {
-                                        material: bunny_material.clone(),
-                                        vertex0: positions[i[0] as usize] * 0.005,
-                                        vertex1: positions[i[1] as usize] * 0.005,
-                                        vertex2: positions[i[2] as usize] * 0.005,
-                                    }))
-                                })
-                                .for_each(drop);
+                        for bunny in &bunnies {
+                            objects.push(Box::new(Scale::new(
+                                bunny.clone(),
+                                Vec3A::splat(bunny_scale),
+                            )));
                         }
                     } else if choose_mat < 0.95 {
                         // metal
@@ -330,14 +394,14 @@ impl Scene {
                         objects.push(Box::new(Sphere {
                             position: center,
                             radius: 0.2,
-                            material: Arc::new(Metal { albedo, fuzz }),
+                            material: Arc::new(Metal::new(albedo, fuzz)),
                         }));
                     } else {
                         // glass
                         objects.push(Box::new(Sphere {
                             position: center,
                             radius: 0.2,
-                            material: Arc::new(Dialectric { ir: 1.5 }),
+                            material: Arc::new(Dialectric::new(1.5)),
                         }));
                     }
                 }
@@ -347,7 +411,7 @@ impl Scene {
         objects.push(Box::new(Sphere {
             position: Vec3A::new(0.0, 1.0, 0.0),
             radius: 1.0,
-            material: Arc::new(Dialectric { ir: 1.5 }),
+            material: Arc::new(Dialectric::new(1.5)),
         }));
         objects.push(Box::new(Sphere {
             position: Vec3A::new(-4.0, 1.0, 0.0),
@@ -359,13 +423,1271 @@ impl Scene {
         objects.push(Box::new(Sphere {
             position: Vec3A::new(4.0, 1.0, 0.0),
             radius: 1.0,
-            material: Arc::new(Metal {
-                albedo: Vec3A::new(0.7, 0.6, 0.5),
-                fuzz: 0.0,
+            material: Arc::new(Metal::new(Vec3A::new(0.7, 0.6, 0.5), 0.0)),
+        }));
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// Bouncing spheres, a spinning globe and a squashing bunny, rendered with motion blur over
+    /// a shutter from time 0 to 1.
+    pub fn motion_blur(&mut self) -> &mut Self {
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        let mut rng: SmallRng = Seeder::from("D4en7gYSdsaaOzPd58BfTa79ugWvcEm5").make_rng();
+
+        for a in -11..11 {
+            for b in -11..11 {
+                let center = Vec3A::new(
+                    a as f32 + 0.9 * rng.gen::<f32>(),
+                    0.2,
+                    b as f32 + 0.9 * rng.gen::<f32>(),
+                );
+
+                if (center - Vec3A::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                    let albedo = Vec3A::new(rng.gen(), rng.gen(), rng.gen())
+                        * Vec3A::new(rng.gen(), rng.gen(), rng.gen());
+                    objects.push(Box::new(MovingSphere::new(
+                        center,
+                        center + Vec3A::new(0.0, rng.gen_range(0.0..0.5), 0.0),
+                        0.0,
+                        1.0,
+                        0.2,
+                        Arc::new(Lambertian {
+                            albedo: Box::new(SolidColor::new(albedo.x, albedo.y, albedo.z)),
+                        }),
+                    )));
+                }
+            }
+        }
+
+        let globe: Arc<dyn Hittable> = Arc::new(Sphere {
+            position: Vec3A::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+            material: Arc::new(Lambertian {
+                albedo: Box::new(ImageTexture::new("earthmap.jpg".into())),
+            }),
+        });
+        objects.push(Box::new(AnimatedInstance::new(
+            globe,
+            vec![
+                TransformKeyframe::new(0.0, Affine3A::from_translation(Vec3::new(0.0, 1.0, 0.0))),
+                TransformKeyframe::new(
+                    1.0,
+                    Affine3A::from_rotation_translation(
+                        Quat::from_rotation_y(PI / 4.0),
+                        Vec3::new(0.0, 1.0, 0.0),
+                    ),
+                ),
+            ],
+        )));
+
+        objects.push(Box::new(Sphere {
+            position: Vec3A::new(4.0, 1.0, 0.0),
+            radius: 1.0,
+            material: Arc::new(Dialectric::new(1.5)),
+        }));
+
+        // A bunny squashing down over the shutter interval
+        let (models, _) =
+            tobj::load_obj("bunny.obj", &GPU_LOAD_OPTIONS).expect("Failed to load obj file");
+        let bunny_material = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.8, 0.8, 0.8)),
+        });
+        let bunny_scale = 0.004;
+        // Bottom of the whole model, which the bunny is squashed towards
+        let bottom = models
+            .iter()
+            .flat_map(|model| model.mesh.positions.chunks(3).map(|p| p[1]))
+            .fold(f32::INFINITY, f32::min);
+
+        for model in &models {
+            let bunny =
+                TriangleMesh::from_tobj(&model.mesh, bunny_material.clone(), Affine3A::IDENTITY);
+            let rest = bunny.positions.clone();
+            let squashed = rest
+                .iter()
+                .map(|p| Vec3A::new(p.x * 1.2, bottom + (p.y - bottom) * 0.6, p.z * 1.2))
+                .collect();
+            let bunny = bunny.with_deformation(vec![rest, squashed], 0.0, 1.0);
+
+            objects.push(Box::new(Translate::new(
+                Scale::new(bunny, Vec3A::splat(bunny_scale)),
+                Vec3A::new(2.0, -bottom * bunny_scale, 2.0),
+            )));
+        }
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// Camera circling the random sphere scene over eight seconds while a metal sphere bounces
+    /// around the middle of it.
+    pub fn turntable(aspect_ratio: f32) -> Animation {
+        let mut background = Scene::new();
+        background.randomize();
+
+        let orbit = (0..=8)
+            .map(|i| {
+                let angle = i as f32 * PI / 4.0;
+                Keyframe {
+                    time: i as f32,
+                    value: Vec3A::new(13.0 * angle.cos(), 2.0, 13.0 * angle.sin()),
+                }
+            })
+            .collect();
+
+        let camera = CameraAnimation {
+            lookfrom: Track::new(orbit, Interpolation::Cubic),
+            lookat: Track::constant(Vec3A::new(0.0, 0.0, 0.0)),
+            vup: Vec3A::new(0.0, 1.0, 0.0),
+            vfov: Track::constant(20.0),
+            aspect_ratio,
+            aperture: Track::constant(0.01),
+            focus_dist: Track::constant(10.0),
+        };
+
+        let bounces = (0..=16)
+            .map(|i| Keyframe {
+                time: i as f32 * 0.5,
+                value: Vec3::new(
+                    2.0 * (i as f32 * PI / 8.0).cos(),
+                    if i % 2 == 0 { 0.5 } else { 1.5 },
+                    2.0 * (i as f32 * PI / 8.0).sin(),
+                ),
+            })
+            .collect();
+
+        let ball = AnimatedObject {
+            object: Arc::new(Sphere {
+                position: Vec3A::new(0.0, 0.0, 0.0),
+                radius: 0.5,
+                material: Arc::new(Metal::new(Vec3A::new(0.8, 0.8, 0.9), 0.0)),
             }),
+            scale: Track::constant(Vec3::ONE),
+            rotation: Track::constant(Quat::IDENTITY),
+            translation: Track::new(bounces, Interpolation::Cubic),
+        };
+
+        Animation {
+            camera,
+            background: Arc::new(BVHAggregate::new(background.objects)),
+            unbounded: background.unbounded.into_iter().map(Arc::from).collect(),
+            objects: vec![ball],
+            frames_per_second: 24.0,
+            shutter: 0.5,
+        }
+    }
+
+    /// Walls and light of the Cornell box, without the boxes inside.
+    fn cornell_walls() -> Vec<Box<dyn Hittable>> {
+        let red = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.65, 0.05, 0.05)),
+        });
+        let white = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.73, 0.73, 0.73)),
+        });
+        let green = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.12, 0.45, 0.15)),
+        });
+        let light = Arc::new(DiffuseLight {
+            emit: Box::new(SolidColor::new(15.0, 15.0, 15.0)),
+        });
+
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+
+        objects.push(Box::new(AxisRect {
+            axis: Axis::X,
+            a0: 0.0,
+            a1: 555.0,
+            b0: 0.0,
+            b1: 555.0,
+            k: 555.0,
+            material: green,
+        }));
+        objects.push(Box::new(AxisRect {
+            axis: Axis::X,
+            a0: 0.0,
+            a1: 555.0,
+            b0: 0.0,
+            b1: 555.0,
+            k: 0.0,
+            material: red,
+        }));
+        objects.push(Box::new(Quad::new(
+            Vec3A::new(343.0, 554.0, 332.0),
+            Vec3A::new(-130.0, 0.0, 0.0),
+            Vec3A::new(0.0, 0.0, -105.0),
+            light,
+        )));
+        for k in [0.0, 555.0] {
+            objects.push(Box::new(AxisRect {
+                axis: Axis::Y,
+                a0: 0.0,
+                a1: 555.0,
+                b0: 0.0,
+                b1: 555.0,
+                k,
+                material: white.clone(),
+            }));
+        }
+        objects.push(Box::new(AxisRect {
+            axis: Axis::Z,
+            a0: 0.0,
+            a1: 555.0,
+            b0: 0.0,
+            b1: 555.0,
+            k: 555.0,
+            material: white,
         }));
 
+        objects
+    }
+
+    /// The Cornell box, lit by an area light in the ceiling. Seen from (278, 278, -800) looking
+    /// at (278, 278, 0) with a vertical field of view of 40 degrees.
+    pub fn cornell_box(&mut self) -> &mut Self {
+        let white = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.73, 0.73, 0.73)),
+        });
+
+        let mut objects = Self::cornell_walls();
+
+        let tall_box = Cuboid::new(
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(165.0, 330.0, 165.0),
+            white.clone(),
+        );
+        objects.push(Box::new(Translate::new(
+            Rotate::new(tall_box, Vec3A::Y, 15.0),
+            Vec3A::new(265.0, 0.0, 295.0),
+        )));
+
+        let short_box = Cuboid::new(
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(165.0, 165.0, 165.0),
+            white,
+        );
+        objects.push(Box::new(Translate::new(
+            Rotate::new(short_box, Vec3A::Y, -18.0),
+            Vec3A::new(130.0, 0.0, 65.0),
+        )));
+
+        self.objects = objects;
+        self.background = Some(Vec3A::new(0.0, 0.0, 0.0));
+
+        self
+    }
+
+    /// The Cornell box with its boxes filled with smoke instead, dark smoke scattering equally
+    /// in all directions in the tall one and white forward scattering smoke in the short one.
+    /// Seen like `cornell_box`.
+    pub fn cornell_smoke(&mut self) -> &mut Self {
+        let mut objects = Self::cornell_walls();
+
+        let boundary = Arc::new(Dialectric::new(1.0));
+
+        let tall_box = Cuboid::new(
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(165.0, 330.0, 165.0),
+            boundary.clone(),
+        );
+        objects.push(Box::new(ConstantMedium::new(
+            Translate::new(
+                Rotate::new(tall_box, Vec3A::Y, 15.0),
+                Vec3A::new(265.0, 0.0, 295.0),
+            ),
+            0.01,
+            Arc::new(Isotropic {
+                albedo: Box::new(SolidColor::new(0.0, 0.0, 0.0)),
+            }),
+        )));
+
+        let short_box = Cuboid::new(
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(165.0, 165.0, 165.0),
+            boundary,
+        );
+        objects.push(Box::new(ConstantMedium::new(
+            Translate::new(
+                Rotate::new(short_box, Vec3A::Y, -18.0),
+                Vec3A::new(130.0, 0.0, 65.0),
+            ),
+            0.01,
+            Arc::new(HenyeyGreenstein {
+                albedo: Box::new(SolidColor::new(1.0, 1.0, 1.0)),
+                g: 0.6,
+            }),
+        )));
+
+        self.objects = objects;
+        self.background = Some(Vec3A::new(0.0, 0.0, 0.0));
+
+        self
+    }
+
+    /// The analytic quadrics and a torus lined up on the ground plane. Seen from (0, 3, 12)
+    /// looking at (0, 1, 0) with a vertical field of view of 30 degrees.
+    pub fn quadrics(&mut self) -> &mut Self {
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(CheckerTexture::new_from_colors(
+                    Vec3A::new(0.2, 0.3, 0.1),
+                    Vec3A::new(0.9, 0.9, 0.9),
+                )),
+            }),
+        ));
+
+        let metal = Arc::new(Metal::new(Vec3A::new(0.8, 0.6, 0.2), 0.05));
+        let earth = Arc::new(Lambertian {
+            albedo: Box::new(ImageTexture::new("earthmap.jpg".into())),
+        });
+        let glass = Arc::new(Dialectric::new(1.5));
+
+        objects.push(Box::new(Cylinder::new(
+            Vec3A::new(-4.5, 0.0, 0.0),
+            0.8,
+            2.0,
+            true,
+            earth.clone(),
+        )));
+        objects.push(Box::new(Cone::new(
+            Vec3A::new(-2.0, 0.0, 0.0),
+            0.9,
+            2.0,
+            true,
+            metal.clone(),
+        )));
+        objects.push(Box::new(Rotate::new(
+            Paraboloid::new(Vec3A::new(0.0, 0.0, 0.0), 0.9, 1.5, false, earth.clone()),
+            Vec3A::X,
+            20.0,
+        )));
+        objects.push(Box::new(Translate::new(
+            Rotate::new(
+                Torus {
+                    center: Vec3A::new(0.0, 0.0, 0.0),
+                    major_radius: 0.8,
+                    minor_radius: 0.3,
+                    material: glass,
+                },
+                Vec3A::X,
+                60.0,
+            ),
+            Vec3A::new(2.3, 1.0, 0.0),
+        )));
+        objects.push(Box::new(Cylinder::new(
+            Vec3A::new(4.5, 0.0, 0.0),
+            0.8,
+            1.5,
+            false,
+            metal,
+        )));
+        objects.push(Box::new(Disk::new(
+            Vec3A::new(4.5, 0.01, 0.0),
+            0.8,
+            0.4,
+            earth,
+        )));
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// A glass lens cut from two spheres, a box with a spherical bite taken out of it and a
+    /// drilled sphere. Seen from (0, 3, 12) looking at (0, 1, 0) with a vertical field of view
+    /// of 30 degrees.
+    pub fn csg(&mut self) -> &mut Self {
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        let glass = Arc::new(Dialectric::new(1.5));
+        let red = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.7, 0.1, 0.1)),
+        });
+        let metal = Arc::new(Metal::new(Vec3A::new(0.8, 0.8, 0.8), 0.1));
+
+        let lens = Csg::new(
+            CsgOp::Intersection,
+            Sphere {
+                position: Vec3A::new(-3.0, 1.2, -1.6),
+                radius: 2.0,
+                material: glass.clone(),
+            },
+            Sphere {
+                position: Vec3A::new(-3.0, 1.2, 1.6),
+                radius: 2.0,
+                material: glass,
+            },
+        );
+        objects.push(Box::new(lens));
+
+        let bitten_box = Csg::new(
+            CsgOp::Difference,
+            Cuboid::new(
+                Vec3A::new(-0.8, 0.0, -0.8),
+                Vec3A::new(0.8, 1.6, 0.8),
+                red.clone(),
+            ),
+            Sphere {
+                position: Vec3A::new(0.8, 1.6, 0.8),
+                radius: 0.9,
+                material: red,
+            },
+        );
+        objects.push(Box::new(bitten_box));
+
+        let drilled_sphere = Csg::new(
+            CsgOp::Difference,
+            Sphere {
+                position: Vec3A::new(3.0, 1.0, 0.0),
+                radius: 1.0,
+                material: metal.clone(),
+            },
+            Csg::new(
+                CsgOp::Union,
+                Cylinder::new(Vec3A::new(3.0, -0.5, 0.0), 0.4, 3.0, true, metal.clone()),
+                Translate::new(
+                    Rotate::new(
+                        Cylinder::new(Vec3A::new(0.0, -1.5, 0.0), 0.4, 3.0, true, metal),
+                        Vec3A::X,
+                        90.0,
+                    ),
+                    Vec3A::new(3.0, 1.0, 0.0),
+                ),
+            ),
+        );
+        objects.push(Box::new(drilled_sphere));
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// Shapes made from signed distance fields: a sphere melting into a rounded box, a torus
+    /// with a bite out of it, a die and a Mandelbulb. Seen from (0, 3, 12) looking at (0, 1, 0) with
+    /// a vertical field of view of 30 degrees.
+    pub fn sdf(&mut self) -> &mut Self {
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        objects.push(Box::new(SdfObject::new(
+            SmoothUnion {
+                a: RoundBox {
+                    center: Vec3A::new(-3.0, 0.6, 0.0),
+                    half_extents: Vec3A::new(1.0, 0.6, 1.0),
+                    radius: 0.2,
+                },
+                b: SdfSphere {
+                    center: Vec3A::new(-3.0, 1.6, 0.0),
+                    radius: 0.6,
+                },
+                k: 0.5,
+            },
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.2, 0.4, 0.8)),
+            }),
+        )));
+
+        objects.push(Box::new(SdfObject::new(
+            Subtraction {
+                a: SdfTorus {
+                    center: Vec3A::new(0.0, 0.4, 0.0),
+                    major_radius: 1.0,
+                    minor_radius: 0.4,
+                },
+                b: SdfSphere {
+                    center: Vec3A::new(0.7, 0.8, 0.7),
+                    radius: 0.6,
+                },
+            },
+            Arc::new(Metal::new(Vec3A::new(0.8, 0.6, 0.2), 0.1)),
+        )));
+
+        objects.push(Box::new(SdfObject::new(
+            Mandelbulb {
+                center: Vec3A::new(3.0, 1.2, 0.0),
+                scale: 1.0,
+                power: 8.0,
+                iterations: 8,
+            },
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.8, 0.3, 0.3)),
+            }),
+        )));
+
+        // Rounded cube with the corners cut off by a sphere, and two dimples on the top
+        objects.push(Box::new(SdfObject::new(
+            Subtraction {
+                a: SdfIntersection {
+                    a: RoundBox {
+                        center: Vec3A::new(-0.8, 0.4, 2.5),
+                        half_extents: Vec3A::splat(0.4),
+                        radius: 0.05,
+                    },
+                    b: SdfSphere {
+                        center: Vec3A::new(-0.8, 0.4, 2.5),
+                        radius: 0.55,
+                    },
+                },
+                b: SdfUnion {
+                    a: SdfSphere {
+                        center: Vec3A::new(-0.95, 0.85, 2.35),
+                        radius: 0.09,
+                    },
+                    b: SdfSphere {
+                        center: Vec3A::new(-0.65, 0.85, 2.65),
+                        radius: 0.09,
+                    },
+                },
+            },
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.9, 0.9, 0.9)),
+            }),
+        )));
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// A patch of grass made of ribbons around a ball of fur made of round curves. Seen from
+    /// (0, 1.5, 6) looking at (0, 0.6, 0) with a vertical field of view of 30 degrees.
+    pub fn grass(&mut self) -> &mut Self {
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.3, 0.2, 0.1)),
+            }),
+        ));
+
+        let mut rng: SmallRng = Seeder::from("grass").make_rng();
+
+        let blade_material = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.2, 0.5, 0.1)),
+        });
+        for _ in 0..4000 {
+            let root = Vec3A::new(rng.gen_range(-4.0..4.0), 0.0, rng.gen_range(-4.0..2.0));
+            let height = rng.gen_range(0.3..0.8);
+            let lean = Vec3A::new(rng.gen_range(-0.3..0.3), 0.0, rng.gen_range(-0.3..0.3));
+
+            // Blades bend over towards their lean, with their width across it
+            let side = match Vec3A::Y.cross(lean).try_normalize() {
+                Some(side) => side,
+                None => Vec3A::X,
+            };
+            let tip_tangent = Vec3A::new(0.0, height * 0.2, 0.0) + lean * 0.5;
+
+            let blade = Curve::new(
+                [
+                    root,
+                    root + Vec3A::new(0.0, height * 0.4, 0.0),
+                    root + Vec3A::new(0.0, height * 0.8, 0.0) + lean * 0.5,
+                    root + Vec3A::new(0.0, height, 0.0) + lean,
+                ],
+                [0.04, 0.002],
+                CurveType::Ribbon,
+                blade_material.clone(),
+            )
+            .with_normals(side.cross(Vec3A::Y), side.cross(tip_tangent));
+
+            for segment in blade.split(2) {
+                objects.push(Box::new(segment));
+            }
+        }
+
+        let fur_material = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.8, 0.6, 0.4)),
+        });
+        let center = Vec3A::new(0.0, 0.7, 0.0);
+        for _ in 0..6000 {
+            let direction = loop {
+                let v = Vec3A::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                );
+                if v.length_squared() <= 1.0 && v.length_squared() > 1e-3 {
+                    break v.normalize();
+                }
+            };
+            let root = center + direction * 0.5;
+            let droop = Vec3A::new(0.0, -0.08, 0.0);
+
+            let hair = Curve::new(
+                [
+                    root,
+                    root + direction * 0.07,
+                    root + direction * 0.14 + droop * 0.5,
+                    root + direction * 0.2 + droop,
+                ],
+                [0.008, 0.001],
+                CurveType::Round,
+                fur_material.clone(),
+            );
+            objects.push(Box::new(hair));
+        }
+        objects.push(Box::new(Sphere {
+            position: center,
+            radius: 0.5,
+            material: fur_material,
+        }));
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// Curves loaded from a file in the format of `load_curves`, with the ground plane below.
+    #[allow(dead_code)]
+    pub fn from_curves(path: &str, curve_type: CurveType) -> Self {
+        let material = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.4, 0.25, 0.1)),
+        });
+
+        let curves = load_curves(path, curve_type, 4, material).expect("Failed to load curves");
+        println!("{} curve segments", curves.len());
+
+        let objects = curves
+            .into_iter()
+            .map(|curve| Box::new(curve) as Box<dyn Hittable>)
+            .collect();
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        Self {
+            objects,
+            unbounded: vec![ground],
+            background: None,
+            medium: None,
+        }
+    }
+
+    /// A wavy Bézier patch intersected directly next to the same patch tessellated coarsely.
+    /// Seen from (0, 4, 8) looking at (0, 0.5, 0) with a vertical field of view of 30 degrees.
+    pub fn patches(&mut self) -> &mut Self {
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        let material = Arc::new(Metal::new(Vec3A::new(0.8, 0.6, 0.2), 0.2));
+
+        let wave = |offset: f32| {
+            let mut control_points = [Vec3A::ZERO; 16];
+            for row in 0..4 {
+                for column in 0..4 {
+                    let height = if (row + column) % 2 == 0 { 0.2 } else { 1.4 };
+                    control_points[row * 4 + column] =
+                        Vec3A::new(offset + column as f32 - 1.5, height, row as f32 - 1.5);
+                }
+            }
+
+            BezierPatch::new(control_points, material.clone())
+        };
+
+        let objects: Vec<Box<dyn Hittable>> =
+            vec![Box::new(wave(-1.8)), Box::new(wave(1.8).tessellate(4))];
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// Patches loaded from a file in the format of `load_patches`, like the Utah teapot, turned
+    /// from z up to y up. Tessellated at `rate` if given, intersected directly otherwise.
+    #[allow(dead_code)]
+    pub fn from_patches(path: &str, rate: Option<usize>) -> Self {
+        let material: Arc<dyn Material> = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.8, 0.8, 0.8)),
+        });
+
+        let patches = load_patches(path, material.clone(), |p| Vec3A::new(p.x, p.z, -p.y))
+            .expect("Failed to load patches");
+        println!("{} patches", patches.len());
+
+        let objects: Vec<Box<dyn Hittable>> = match rate {
+            Some(rate) => vec![Box::new(tessellate_patches(&patches, rate, material))],
+            None => patches
+                .into_iter()
+                .map(|patch| Box::new(patch) as Box<dyn Hittable>)
+                .collect(),
+        };
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        Self {
+            objects,
+            unbounded: vec![ground],
+            background: None,
+            medium: None,
+        }
+    }
+
+    /// A cube cage smoothed by three levels of Catmull-Clark subdivision, the same cube with the
+    /// edges around its top kept sharp, and an octahedron smoothed by Loop subdivision.
+    /// Seen from (0, 4, 9) looking at (0, 0.8, 0) with a vertical field of view of 30 degrees.
+    pub fn subdivision(&mut self) -> &mut Self {
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        let material: Arc<dyn Material> = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.8, 0.3, 0.2)),
+        });
+
+        let cube = |offset: Vec3A| {
+            let positions = (0..8)
+                .map(|i| {
+                    offset
+                        + Vec3A::new(
+                            (i & 1) as f32 - 0.5,
+                            (i >> 1 & 1) as f32 - 0.5,
+                            (i >> 2 & 1) as f32 - 0.5,
+                        )
+                })
+                .collect();
+
+            PolyMesh {
+                positions,
+                faces: vec![
+                    vec![0, 2, 3, 1],
+                    vec![4, 5, 7, 6],
+                    vec![0, 1, 5, 4],
+                    vec![2, 6, 7, 3],
+                    vec![0, 4, 6, 2],
+                    vec![1, 3, 7, 5],
+                ],
+                ..Default::default()
+            }
+        };
+
+        let smooth = cube(Vec3A::new(-2.2, 0.5, 0.0)).subdivide(3);
+        let creased = cube(Vec3A::new(0.0, 0.5, 0.0))
+            .with_crease(2, 3, f32::INFINITY)
+            .with_crease(3, 7, f32::INFINITY)
+            .with_crease(7, 6, f32::INFINITY)
+            .with_crease(6, 2, f32::INFINITY)
+            .subdivide(3);
+
+        let octahedron = PolyMesh {
+            positions: vec![
+                Vec3A::new(2.2, 1.3, 0.0),
+                Vec3A::new(2.2, -0.1, 0.0),
+                Vec3A::new(2.9, 0.6, 0.0),
+                Vec3A::new(1.5, 0.6, 0.0),
+                Vec3A::new(2.2, 0.6, 0.7),
+                Vec3A::new(2.2, 0.6, -0.7),
+            ],
+            faces: vec![
+                vec![0, 4, 2],
+                vec![0, 2, 5],
+                vec![0, 5, 3],
+                vec![0, 3, 4],
+                vec![1, 2, 4],
+                vec![1, 5, 2],
+                vec![1, 3, 5],
+                vec![1, 4, 3],
+            ],
+            ..Default::default()
+        }
+        .subdivide(3);
+
+        let objects: Vec<Box<dyn Hittable>> = vec![
+            Box::new(smooth.to_triangle_mesh(material.clone())),
+            Box::new(creased.to_triangle_mesh(material.clone())),
+            Box::new(octahedron.to_triangle_mesh(material)),
+        ];
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// Every model of an obj file subdivided `levels` times, Loop subdivision for triangle
+    /// meshes and Catmull-Clark for the rest.
+    #[allow(dead_code)]
+    pub fn from_obj_subdivided(path: &str, levels: u32) -> Self {
+        let (models, _) =
+            tobj::load_obj(path, &SUBDIVISION_LOAD_OPTIONS).expect("Failed to load obj file");
+
+        let material: Arc<dyn Material> = Arc::new(Lambertian {
+            albedo: Box::new(SolidColor::new(0.8, 0.8, 0.8)),
+        });
+
+        let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
+        let mut totsize: usize = 0;
+
+        for model in models {
+            let mesh = PolyMesh::from_tobj(&model.mesh)
+                .subdivide(levels)
+                .to_triangle_mesh(material.clone());
+
+            totsize += mesh.indices.len();
+            objects.push(Box::new(mesh));
+        }
+
+        println!("{} triangles", totsize);
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        Self {
+            objects,
+            unbounded: vec![ground],
+            background: None,
+            medium: None,
+        }
+    }
+
+    /// A flat square displaced by the earth map it is textured with, so land and ice rise above
+    /// the sea.
+    /// Seen from (0, 4, 4) looking at (0, 0.3, 0) with a vertical field of view of 40 degrees.
+    pub fn displacement(&mut self) -> &mut Self {
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.5, 0.5, 0.5)),
+            }),
+        ));
+
+        let earth = Arc::new(Lambertian {
+            albedo: Box::new(ImageTexture::new("earthmap.jpg".to_string())),
+        });
+        let square = TriangleMesh::new(
+            vec![
+                Vec3A::new(-2.0, 0.01, -1.0),
+                Vec3A::new(2.0, 0.01, -1.0),
+                Vec3A::new(2.0, 0.01, 1.0),
+                Vec3A::new(-2.0, 0.01, 1.0),
+            ],
+            vec![Vec3A::Y; 4],
+            vec![
+                Vec2::new(0.0, 1.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(0.0, 0.0),
+            ],
+            vec![[0, 2, 1], [0, 3, 2]],
+            earth,
+        );
+        let relief = displace(
+            &square,
+            &ImageTexture::new("earthmap.jpg".to_string()),
+            0.15,
+            0.02,
+        );
+
+        let objects: Vec<Box<dyn Hittable>> = vec![Box::new(relief)];
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// The earth map as terrain, with ice and land rising above the sea, textured with the
+    /// same map.
+    /// Seen from (0, 4, 4) looking at (0, 0.3, 0) with a vertical field of view of 40 degrees.
+    pub fn heightfield(&mut self) -> &mut Self {
+        *self = Self::from_heightmap("earthmap.jpg", Vec3A::new(4.0, 0.3, 2.0));
+
+        self
+    }
+
+    /// Terrain from a grayscale height image, centered on the origin and `size` in extent,
+    /// textured with the same image.
+    #[allow(dead_code)]
+    pub fn from_heightmap(path: &str, size: Vec3A) -> Self {
+        let image = ImageTexture::new(path.to_string());
+        println!("{}x{} heights", image.width, image.height);
+
+        let origin = Vec3A::new(-0.5 * size.x, 0.01, -0.5 * size.z);
+        let terrain = Heightfield::from_image(
+            &image,
+            origin,
+            size,
+            Arc::new(Lambertian {
+                albedo: Box::new(ImageTexture::new(path.to_string())),
+            }),
+        );
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.5, 0.5, 0.5)),
+            }),
+        ));
+
+        Self {
+            objects: vec![Box::new(terrain)],
+            unbounded: vec![ground],
+            background: None,
+            medium: None,
+        }
+    }
+
+    /// A cloud of Perlin noise over the ground, next to a small fire glowing where it is dense.
+    /// Seen from (0, 2, 9) looking at (0, 1.2, 0) with a vertical field of view of 30 degrees.
+    pub fn clouds(&mut self) -> &mut Self {
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.4, 0.4, 0.4)),
+            }),
+        ));
+
+        let cloud = HeterogeneousMedium::new(
+            VoxelGrid::from_noise([96, 48, 48], 5),
+            30.0,
+            AABB::new(Vec3A::new(-2.5, 1.0, -1.25), Vec3A::new(1.5, 3.0, 0.75)),
+            Vec3A::splat(0.95),
+            0.85,
+            None,
+        );
+
+        let flames = VoxelGrid::from_noise([32, 48, 32], 4);
+        let fire = HeterogeneousMedium::new(
+            flames.clone(),
+            20.0,
+            AABB::new(Vec3A::new(1.6, 0.0, 0.5), Vec3A::new(2.4, 1.2, 1.3)),
+            Vec3A::splat(0.5),
+            0.0,
+            Some((flames, Vec3A::new(12.0, 4.0, 1.0))),
+        );
+
+        self.objects = vec![Box::new(cloud), Box::new(fire)];
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// A medium from a voxel grid file in the format of `VoxelGrid::load`, filling a box
+    /// `size` in extent standing on the ground.
+    #[allow(dead_code)]
+    pub fn from_voxel_grid(path: &str, size: Vec3A, density: f32) -> Self {
+        let grid = VoxelGrid::load(path).expect("Failed to load voxel grid");
+        println!("{:?} voxels", grid.dims);
+
+        let bounds = AABB::new(
+            Vec3A::new(-0.5 * size.x, 0.0, -0.5 * size.z),
+            Vec3A::new(0.5 * size.x, size.y, 0.5 * size.z),
+        );
+        let medium = HeterogeneousMedium::new(grid, density, bounds, Vec3A::splat(0.9), 0.5, None);
+
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        Self {
+            objects: vec![Box::new(medium)],
+            unbounded: vec![ground],
+            background: None,
+            medium: None,
+        }
+    }
+
+    /// Spheres fading into fog, in front of them a green glass sphere whose color comes from
+    /// the medium inside it, darker where light travels further through it.
+    /// Seen from (0, 1.5, 8) looking at (0, 1, 0) with a vertical field of view of 30 degrees.
+    pub fn fog(&mut self) -> &mut Self {
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.5, 0.5, 0.5)),
+            }),
+        ));
+
+        let mut objects: Vec<Box<dyn Hittable>> = vec![Box::new(WithMedia::new(
+            Sphere {
+                position: Vec3A::new(0.0, 1.0, 2.0),
+                radius: 1.0,
+                material: Arc::new(Dialectric::new(1.5)),
+            },
+            MediumInterface {
+                interior: Some(Arc::new(HomogeneousMedium::absorbing(Vec3A::new(
+                    1.5, 0.2, 1.2,
+                )))),
+                exterior: None,
+            },
+        ))];
+
+        for i in 0..6 {
+            let material: Arc<dyn Material> = if i % 2 == 0 {
+                Arc::new(Lambertian {
+                    albedo: Box::new(SolidColor::new(0.7, 0.2, 0.1)),
+                })
+            } else {
+                Arc::new(Metal::new(Vec3A::new(0.8, 0.8, 0.8), 0.05))
+            };
+
+            objects.push(Box::new(Sphere {
+                position: Vec3A::new(if i % 2 == 0 { -2.0 } else { 2.0 }, 1.0, -3.0 * i as f32),
+                radius: 1.0,
+                material,
+            }));
+        }
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+        self.medium = Some(Arc::new(HomogeneousMedium::new(
+            Vec3A::splat(0.01),
+            Vec3A::splat(0.08),
+            0.5,
+        )));
+
+        self
+    }
+
+    /// Spheres of the same tinted glass growing in size, the larger ones darker and deeper in
+    /// color, in front of a noisy wall.
+    /// Seen from (0, 2, 9) looking at (0, 0.8, 0) with a vertical field of view of 30 degrees.
+    pub fn colored_glass(&mut self) -> &mut Self {
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.8, 0.8, 0.8)),
+            }),
+        ));
+        let wall: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::new(0.0, 0.0, -3.0),
+            Vec3A::Z,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        let glass =
+            Arc::new(Dialectric::new(1.5).with_transmittance(Vec3A::new(0.3, 0.7, 0.9), 0.5));
+
+        let objects: Vec<Box<dyn Hittable>> = [(-2.6, 0.25), (-1.6, 0.5), (0.2, 0.9), (2.4, 1.2)]
+            .iter()
+            .map(|&(x, radius)| {
+                Box::new(Sphere {
+                    position: Vec3A::new(x, radius, 0.0),
+                    radius,
+                    material: glass.clone(),
+                }) as Box<dyn Hittable>
+            })
+            .collect();
+
+        self.objects = objects;
+        self.unbounded = vec![ground, wall];
+
+        self
+    }
+
+    /// Prisms of crown glass on the left and of strongly dispersive flint glass on the right,
+    /// in front of a row of thin lights on a dark wall, which appear split into rainbows through
+    /// them when rendered with `--spectral`.
+    /// Seen from (0, 1, 6) looking at (0, 1, 0) with a vertical field of view of 30 degrees.
+    pub fn prism(&mut self) -> &mut Self {
+        // Schott BK7, the common crown glass
+        let crown = Arc::new(Dialectric::new(1.5).with_dispersion(Dispersion::Sellmeier {
+            b: [1.039_612, 0.231_792_34, 1.010_469_5],
+            c: [0.006_000_699, 0.020_017_914, 103.560_65],
+        }));
+        let flint =
+            Arc::new(Dialectric::new(1.5).with_dispersion(Dispersion::Cauchy { a: 1.6, b: 0.05 }));
+
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+        for (x, glass) in [(-1.3, crown), (1.3, flint)] {
+            // Equilateral triangle over the xz plane, counter-clockwise seen from above
+            let corners = [
+                Vec3A::new(x - 1.0, 0.0, 0.577),
+                Vec3A::new(x + 1.0, 0.0, 0.577),
+                Vec3A::new(x, 0.0, -1.155),
+            ];
+            let height = Vec3A::new(0.0, 2.0, 0.0);
+
+            for i in 0..3 {
+                let edge = corners[(i + 1) % 3] - corners[i];
+                objects.push(Box::new(Quad::new(corners[i], edge, height, glass.clone())));
+            }
+            objects.push(Box::new(Triangle::new(
+                corners[0],
+                corners[2],
+                corners[1],
+                glass.clone(),
+            )));
+            objects.push(Box::new(Triangle::new(
+                corners[0] + height,
+                corners[1] + height,
+                corners[2] + height,
+                glass,
+            )));
+        }
+
+        let light = Arc::new(DiffuseLight {
+            emit: Box::new(SolidColor::new(4.0, 4.0, 4.0)),
+        });
+        for i in -8..=8 {
+            objects.push(Box::new(Quad::new(
+                Vec3A::new(i as f32 * 0.8 - 0.05, -1.0, -4.0),
+                Vec3A::new(0.1, 0.0, 0.0),
+                Vec3A::new(0.0, 4.0, 0.0),
+                light.clone(),
+            )));
+        }
+
+        let wall: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::new(0.0, 0.0, -4.01),
+            Vec3A::Z,
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.05, 0.05, 0.05)),
+            }),
+        ));
+
+        self.objects = objects;
+        self.unbounded = vec![wall];
+        self.background = Some(Vec3A::ZERO);
+
+        self
+    }
+
+    /// Gold spheres getting rougher from left to right, and brushed steel ones below them
+    /// getting more anisotropic, on a marbled floor.
+    /// Seen from (0, 3, 10) looking at (0, 1, 0) with a vertical field of view of 30 degrees.
+    pub fn metals(&mut self) -> &mut Self {
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        let gold = Vec3A::new(1.0, 0.78, 0.34);
+        let steel = Vec3A::new(0.56, 0.57, 0.58);
+
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+        for i in 0..5 {
+            let x = (i as f32 - 2.0) * 1.2;
+            let t = i as f32 / 4.0;
+
+            objects.push(Box::new(Sphere {
+                position: Vec3A::new(x, 2.0, 0.0),
+                radius: 0.5,
+                material: Arc::new(Metal::new(gold, t * 0.6)),
+            }));
+            objects.push(Box::new(Sphere {
+                position: Vec3A::new(x, 0.5, 0.0),
+                radius: 0.5,
+                material: Arc::new(Metal::anisotropic(steel, 0.4, t)),
+            }));
+        }
+
+        self.objects = objects;
+        self.unbounded = vec![ground];
+
+        self
+    }
+
+    /// Glass spheres getting more frosted from left to right in front of a noisy wall, which
+    /// blurs more and more behind them.
+    /// Seen from (0, 1, 8) looking at (0, 0.6, 0) with a vertical field of view of 30 degrees.
+    pub fn frosted_glass(&mut self) -> &mut Self {
+        let ground: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::ZERO,
+            Vec3A::Y,
+            Arc::new(Lambertian {
+                albedo: Box::new(SolidColor::new(0.8, 0.8, 0.8)),
+            }),
+        ));
+        let wall: Box<dyn Hittable> = Box::new(Plane::new(
+            Vec3A::new(0.0, 0.0, -2.0),
+            Vec3A::Z,
+            Arc::new(Lambertian {
+                albedo: Box::new(NoiseTexture::new()),
+            }),
+        ));
+
+        let objects: Vec<Box<dyn Hittable>> = (0..4)
+            .map(|i| {
+                Box::new(Sphere {
+                    position: Vec3A::new((i as f32 - 1.5) * 1.4, 0.6, 0.0),
+                    radius: 0.6,
+                    material: Arc::new(RoughDialectric::new(1.5, i as f32 * 0.2)),
+                }) as Box<dyn Hittable>
+            })
+            .collect();
+
         self.objects = objects;
+        self.unbounded = vec![ground, wall];
 
         self
     }
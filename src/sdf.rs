@@ -0,0 +1,304 @@
+use std::sync::Arc;
+
+use glam::Vec3A;
+
+use crate::{
+    aabb::AABB,
+    geometry::Sphere,
+    hittable::Hittable,
+    material::Material,
+    ray::{HitRecord, Ray},
+};
+
+/// Signed distance field, negative inside the shape. Distances may be underestimated, which
+/// only makes sphere tracing take more steps, but never overestimated.
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: Vec3A) -> f32;
+    fn bounding_box(&self) -> AABB;
+}
+
+pub struct SdfSphere {
+    pub center: Vec3A,
+    pub radius: f32,
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: Vec3A) -> f32 {
+        (p - self.center).length() - self.radius
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::new(
+            self.center - Vec3A::splat(self.radius),
+            self.center + Vec3A::splat(self.radius),
+        )
+    }
+}
+
+/// Box with edges rounded off by `radius`, within `half_extents` of the center.
+pub struct RoundBox {
+    pub center: Vec3A,
+    pub half_extents: Vec3A,
+    pub radius: f32,
+}
+
+impl Sdf for RoundBox {
+    fn distance(&self, p: Vec3A) -> f32 {
+        let q = (p - self.center).abs() - self.half_extents + Vec3A::splat(self.radius);
+
+        q.max(Vec3A::ZERO).length() + q.max_element().min(0.0) - self.radius
+    }
+
+    fn bounding_box(&self) -> AABB {
+        AABB::new(
+            self.center - self.half_extents,
+            self.center + self.half_extents,
+        )
+    }
+}
+
+/// Torus around the y axis, like `geometry::Torus`.
+pub struct SdfTorus {
+    pub center: Vec3A,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, p: Vec3A) -> f32 {
+        let p = p - self.center;
+        let ring = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+
+        (ring * ring + p.y * p.y).sqrt() - self.minor_radius
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let extent = Vec3A::new(
+            self.major_radius + self.minor_radius,
+            self.minor_radius,
+            self.major_radius + self.minor_radius,
+        );
+
+        AABB::new(self.center - extent, self.center + extent)
+    }
+}
+
+/// The Mandelbulb fractal with its pole along the y axis, about `scale` in radius. A `power` of
+/// 8 gives the classic shape, more `iterations` give finer detail.
+pub struct Mandelbulb {
+    pub center: Vec3A,
+    pub scale: f32,
+    pub power: f32,
+    pub iterations: u32,
+}
+
+impl Sdf for Mandelbulb {
+    fn distance(&self, p: Vec3A) -> f32 {
+        let c = (p - self.center) / self.scale;
+
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = z.length();
+
+        for _ in 0..self.iterations {
+            if r > 2.0 {
+                break;
+            }
+
+            // The angles are undefined at the origin, which stays inside anyway
+            if r < 1e-6 {
+                return 0.0;
+            }
+
+            // Raise z to the power in spherical coordinates
+            let theta = (z.y / r).acos() * self.power;
+            let phi = z.z.atan2(z.x) * self.power;
+            let zr = r.powf(self.power);
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+
+            z =
+                zr * Vec3A::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ) + c;
+            r = z.length();
+        }
+
+        if r < 1e-6 {
+            return 0.0;
+        }
+
+        0.5 * r.ln() * r / dr * self.scale
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let extent = Vec3A::splat(1.2 * self.scale);
+
+        AABB::new(self.center - extent, self.center + extent)
+    }
+}
+
+pub struct Union<A: Sdf, B: Sdf> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Union<A, B> {
+    fn distance(&self, p: Vec3A) -> f32 {
+        self.a.distance(p).min(self.b.distance(p))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.a.bounding_box().join(&self.b.bounding_box())
+    }
+}
+
+pub struct Intersection<A: Sdf, B: Sdf> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Intersection<A, B> {
+    fn distance(&self, p: Vec3A) -> f32 {
+        self.a.distance(p).max(self.b.distance(p))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.a.bounding_box().overlap(&self.b.bounding_box())
+    }
+}
+
+/// `a` with `b` cut out of it.
+pub struct Subtraction<A: Sdf, B: Sdf> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Subtraction<A, B> {
+    fn distance(&self, p: Vec3A) -> f32 {
+        self.a.distance(p).max(-self.b.distance(p))
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.a.bounding_box()
+    }
+}
+
+/// Union blending the shapes into each other where they are closer than `k`.
+pub struct SmoothUnion<A: Sdf, B: Sdf> {
+    pub a: A,
+    pub b: B,
+    pub k: f32,
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SmoothUnion<A, B> {
+    fn distance(&self, p: Vec3A) -> f32 {
+        let da = self.a.distance(p);
+        let db = self.b.distance(p);
+        let h = (0.5 + 0.5 * (db - da) / self.k).clamp(0.0, 1.0);
+
+        db + (da - db) * h - self.k * h * (1.0 - h)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        // The blend bulges out by at most a quarter of `k`
+        let aabb = self.a.bounding_box().join(&self.b.bounding_box());
+        let padding = Vec3A::splat(self.k / 4.0);
+
+        AABB::new(aabb.minimum - padding, aabb.maximum + padding)
+    }
+}
+
+/// Places a signed distance field in the scene, intersected by sphere tracing through its
+/// bounding box. Normals are the gradient of the field, found by central differences.
+pub struct SdfObject<S: Sdf> {
+    pub sdf: S,
+    pub material: Arc<dyn Material>,
+    aabb: AABB,
+}
+
+impl<S: Sdf> SdfObject<S> {
+    /// Distance to the surface at which the ray is considered to hit it.
+    const EPSILON: f32 = 1e-4;
+    const MAX_STEPS: usize = 512;
+
+    pub fn new(sdf: S, material: Arc<dyn Material>) -> Self {
+        // Leave a margin around the shape, so that rays entering the box are clearly outside
+        let aabb = sdf.bounding_box();
+        let margin = Vec3A::splat(2.0 * Self::EPSILON);
+
+        Self {
+            aabb: AABB::new(aabb.minimum - margin, aabb.maximum + margin),
+            sdf,
+            material,
+        }
+    }
+
+    fn normal(&self, p: Vec3A) -> Vec3A {
+        let h = Self::EPSILON;
+        let dx = Vec3A::new(h, 0.0, 0.0);
+        let dy = Vec3A::new(0.0, h, 0.0);
+        let dz = Vec3A::new(0.0, 0.0, h);
+
+        Vec3A::new(
+            self.sdf.distance(p + dx) - self.sdf.distance(p - dx),
+            self.sdf.distance(p + dy) - self.sdf.distance(p - dy),
+            self.sdf.distance(p + dz) - self.sdf.distance(p - dz),
+        )
+        .normalize_or_zero()
+    }
+}
+
+impl<S: Sdf> Hittable for SdfObject<S> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (enter, exit) = r.aabb_interval(self.aabb)?;
+        let t_end = exit.min(t_max);
+        let mut t = enter.max(t_min);
+
+        let speed = r.direction.length();
+
+        // Rays starting inside the shape, e.g. refracted ones, trace towards where they leave
+        let start = r.at(t);
+        let start_distance = self.sdf.distance(start);
+        let mut leaving_surface = start_distance.abs() < Self::EPSILON;
+        let side = if leaving_surface {
+            // Scattered off this surface, the direction tells which side the ray is going to
+            self.normal(start).dot(r.direction).signum()
+        } else {
+            start_distance.signum()
+        };
+
+        for _ in 0..Self::MAX_STEPS {
+            if t > t_end {
+                return None;
+            }
+
+            let p = r.at(t);
+            let distance = side * self.sdf.distance(p);
+
+            if leaving_surface {
+                if distance < Self::EPSILON {
+                    t += Self::EPSILON / speed;
+                    continue;
+                }
+                leaving_surface = false;
+            }
+
+            if distance < Self::EPSILON {
+                let outward_normal = self.normal(p);
+                let (u, v) = Sphere::get_sphere_uv(outward_normal);
+
+                return Some(HitRecord::new(r, t, outward_normal, u, v, &self.material));
+            }
+
+            t += distance / speed;
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
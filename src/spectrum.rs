@@ -0,0 +1,121 @@
+use std::sync::OnceLock;
+
+use glam::{Mat3A, Vec3A, Vec4};
+
+/// Range of visible wavelengths in nanometres that spectral rendering samples.
+pub const LAMBDA_MIN: f32 = 380.0;
+pub const LAMBDA_MAX: f32 = 720.0;
+
+/// Wavelengths traced together along a path, a hero wavelength sampled uniformly and the
+/// others spread evenly over the range from it. Values carried along a path are `Vec4`s with
+/// one entry per wavelength.
+#[derive(Clone, Copy, Debug)]
+pub struct SampledWavelengths {
+    pub lambda: Vec4,
+    pub pdf: Vec4,
+}
+
+impl SampledWavelengths {
+    /// Samples the wavelengths from a uniform random number `u`.
+    pub fn sample(u: f32) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = LAMBDA_MIN + u * range;
+
+        let lambda = [0.0, 1.0, 2.0, 3.0].map(|i| {
+            let lambda = hero + i * range / 4.0;
+            if lambda > LAMBDA_MAX {
+                lambda - range
+            } else {
+                lambda
+            }
+        });
+
+        Self {
+            lambda: Vec4::from_array(lambda),
+            pdf: Vec4::splat(1.0 / range),
+        }
+    }
+
+    pub fn hero(&self) -> f32 {
+        self.lambda.x
+    }
+
+    /// Drops all but the hero wavelength, for when the path depends on the wavelength like
+    /// through dispersive glass.
+    pub fn terminate_secondary(&mut self) {
+        if self.pdf.y == 0.0 {
+            return;
+        }
+
+        self.pdf = Vec4::new(self.pdf.x / 4.0, 0.0, 0.0, 0.0);
+    }
+
+    /// Turns an RGB color, like a reflectance or an emission, into a smooth spectrum over
+    /// blue, green and red bands evaluated at the wavelengths. The bands add up to one
+    /// everywhere, so white stays flat and reflectances stay below one.
+    pub fn uplift(&self, rgb: Vec3A) -> Vec4 {
+        let values = self.lambda.to_array().map(|lambda| {
+            let green_start = smoothstep(470.0, 510.0, lambda);
+            let red_start = smoothstep(565.0, 600.0, lambda);
+
+            rgb.x * red_start + rgb.y * (green_start - red_start) + rgb.z * (1.0 - green_start)
+        });
+
+        Vec4::from_array(values)
+    }
+
+    /// Converts the values at the wavelengths into a linear sRGB estimate of the spectrum,
+    /// balanced so that a flat spectrum is white.
+    pub fn to_rgb(self, values: Vec4) -> Vec3A {
+        let mut xyz = Vec3A::ZERO;
+        for i in 0..4 {
+            if self.pdf[i] > 0.0 {
+                xyz += values[i] * color_matching(self.lambda[i]) / self.pdf[i];
+            }
+        }
+
+        XYZ_TO_SRGB * (xyz / 4.0) / white_rgb()
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Gaussian with different widths below and above its mean.
+fn lobe(lambda: f32, mean: f32, below: f32, above: f32) -> f32 {
+    let t = (lambda - mean) / if lambda < mean { below } else { above };
+    (-0.5 * t * t).exp()
+}
+
+/// The CIE 1931 color matching functions by the multi-lobe fit of Wyman, Sloan and Shirley.
+pub fn color_matching(lambda: f32) -> Vec3A {
+    Vec3A::new(
+        1.056 * lobe(lambda, 599.8, 37.9, 31.0) + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+            - 0.065 * lobe(lambda, 501.1, 20.4, 26.2),
+        0.821 * lobe(lambda, 568.8, 46.9, 40.5) + 0.286 * lobe(lambda, 530.9, 16.3, 31.1),
+        1.217 * lobe(lambda, 437.0, 11.8, 36.0) + 0.681 * lobe(lambda, 459.0, 26.0, 13.8),
+    )
+}
+
+const XYZ_TO_SRGB: Mat3A = Mat3A::from_cols(
+    Vec3A::new(3.240_454_2, -0.969_266, 0.055_643_4),
+    Vec3A::new(-1.537_138_5, 1.876_010_8, -0.204_025_9),
+    Vec3A::new(-0.498_531_4, 0.041_556, 1.057_225_2),
+);
+
+/// Color of a flat spectrum of one, integrated once over the sampled range.
+fn white_rgb() -> Vec3A {
+    static WHITE: OnceLock<Vec3A> = OnceLock::new();
+
+    *WHITE.get_or_init(|| {
+        let steps = 1000;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as f32;
+        let xyz = (0..steps)
+            .map(|i| color_matching(LAMBDA_MIN + (i as f32 + 0.5) * step) * step)
+            .fold(Vec3A::ZERO, |sum, value| sum + value);
+
+        XYZ_TO_SRGB * xyz
+    })
+}
@@ -0,0 +1,457 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
+
+use glam::{Vec2, Vec3A};
+use tobj::LoadOptions;
+
+use crate::{material::Material, mesh::TriangleMesh};
+
+/// Options to load models for subdivision with. Faces are kept as they are, and positions keep
+/// their own indices so that faces on both sides of a texture seam still share vertices.
+pub const SUBDIVISION_LOAD_OPTIONS: LoadOptions = LoadOptions {
+    single_index: false,
+    triangulate: false,
+    ignore_points: true,
+    ignore_lines: true,
+};
+
+/// Polygon mesh with shared vertices, refined by subdivision before it is turned into a
+/// `TriangleMesh`. Texture coordinates are stored per face corner and interpolated linearly
+/// within faces.
+#[derive(Clone, Debug, Default)]
+pub struct PolyMesh {
+    pub positions: Vec<Vec3A>,
+    /// Vertex indices of every face, counter-clockwise.
+    pub faces: Vec<Vec<u32>>,
+    /// Texture coordinates of the corners of every face, empty if the mesh has none.
+    pub face_uvs: Vec<Vec<Vec2>>,
+    /// Sharpness of creased edges, keyed by their vertices with the smaller index first. Edges
+    /// stay sharp for as many levels as their sharpness, a fraction blends the smooth and sharp
+    /// rules. Boundary edges are always sharp.
+    pub creases: HashMap<(u32, u32), f32>,
+}
+
+fn edge_key(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+/// Faces around an edge and where its new vertex ends up.
+struct Edge {
+    faces: Vec<usize>,
+    point: u32,
+}
+
+/// Topology of a mesh needed by both schemes.
+struct Adjacency {
+    edges: HashMap<(u32, u32), Edge>,
+    /// Neighbouring vertices of every vertex, one per edge.
+    neighbours: Vec<Vec<u32>>,
+    /// Faces around every vertex.
+    vertex_faces: Vec<Vec<usize>>,
+}
+
+impl PolyMesh {
+    /// Creates a mesh from a model loaded with `SUBDIVISION_LOAD_OPTIONS`.
+    pub fn from_tobj(mesh: &tobj::Mesh) -> Self {
+        let positions = mesh
+            .positions
+            .chunks(3)
+            .map(|i| Vec3A::new(i[0], i[1], i[2]))
+            .collect();
+
+        let arities = if mesh.face_arities.is_empty() {
+            vec![3; mesh.indices.len() / 3]
+        } else {
+            mesh.face_arities.clone()
+        };
+
+        let mut faces = vec![];
+        let mut face_uvs = vec![];
+        let mut start = 0;
+        for arity in arities {
+            let end = start + arity as usize;
+            faces.push(mesh.indices[start..end].to_vec());
+
+            if !mesh.texcoords.is_empty() && !mesh.texcoord_indices.is_empty() {
+                face_uvs.push(
+                    mesh.texcoord_indices[start..end]
+                        .iter()
+                        .map(|&i| {
+                            Vec2::new(
+                                mesh.texcoords[2 * i as usize],
+                                mesh.texcoords[2 * i as usize + 1],
+                            )
+                        })
+                        .collect(),
+                );
+            }
+
+            start = end;
+        }
+
+        Self {
+            positions,
+            faces,
+            face_uvs,
+            creases: HashMap::new(),
+        }
+    }
+
+    /// Marks the edge between two vertices as creased with `sharpness`.
+    pub fn with_crease(mut self, a: u32, b: u32, sharpness: f32) -> Self {
+        self.creases.insert(edge_key(a, b), sharpness);
+        self
+    }
+
+    pub fn is_triangle_mesh(&self) -> bool {
+        self.faces.iter().all(|face| face.len() == 3)
+    }
+
+    /// Applies `levels` steps of Loop subdivision if the mesh is made of triangles, and of
+    /// Catmull-Clark subdivision otherwise, which turns any polygons into quads.
+    pub fn subdivide(&self, levels: u32) -> PolyMesh {
+        let mut mesh = self.clone();
+
+        for _ in 0..levels {
+            mesh = if mesh.is_triangle_mesh() {
+                mesh.loop_step()
+            } else {
+                mesh.catmull_clark_step()
+            };
+        }
+
+        mesh
+    }
+
+    fn adjacency(&self, first_edge_point: u32) -> Adjacency {
+        let mut edges: HashMap<(u32, u32), Edge> = HashMap::new();
+        let mut neighbours = vec![vec![]; self.positions.len()];
+        let mut vertex_faces = vec![vec![]; self.positions.len()];
+
+        for (index, face) in self.faces.iter().enumerate() {
+            for (corner, &a) in face.iter().enumerate() {
+                let b = face[(corner + 1) % face.len()];
+                vertex_faces[a as usize].push(index);
+
+                match edges.entry(edge_key(a, b)) {
+                    Entry::Occupied(mut edge) => edge.get_mut().faces.push(index),
+                    Entry::Vacant(edge) => {
+                        edge.insert(Edge {
+                            faces: vec![index],
+                            point: 0,
+                        });
+                        neighbours[a as usize].push(b);
+                        neighbours[b as usize].push(a);
+                    }
+                }
+            }
+        }
+
+        // Number the new edge vertices in a fixed order, the map iterates in any order
+        let mut keys = edges.keys().copied().collect::<Vec<_>>();
+        keys.sort_unstable();
+        for (i, key) in keys.iter().enumerate() {
+            edges.get_mut(key).unwrap().point = first_edge_point + i as u32;
+        }
+
+        Adjacency {
+            edges,
+            neighbours,
+            vertex_faces,
+        }
+    }
+
+    /// Sharpness of an edge, infinite on the boundary.
+    fn sharpness(&self, adjacency: &Adjacency, a: u32, b: u32) -> f32 {
+        let key = edge_key(a, b);
+
+        if adjacency.edges[&key].faces.len() != 2 {
+            f32::INFINITY
+        } else {
+            self.creases.get(&key).copied().unwrap_or(0.0)
+        }
+    }
+
+    /// Moves a vertex by the crease rules if two or more sharp edges meet at it, given where
+    /// the smooth rule would put it.
+    fn crease_vertex(&self, adjacency: &Adjacency, vertex: u32, smooth: Vec3A) -> Vec3A {
+        let position = self.positions[vertex as usize];
+
+        let sharp = adjacency.neighbours[vertex as usize]
+            .iter()
+            .map(|&neighbour| (neighbour, self.sharpness(adjacency, vertex, neighbour)))
+            .filter(|(_, sharpness)| *sharpness > 0.0)
+            .collect::<Vec<_>>();
+
+        if sharp.len() < 2 {
+            return smooth;
+        }
+
+        // Vertices on a single face, like the corners of an open grid, stay where they are
+        let sharp_position = if sharp.len() == 2
+            && adjacency.vertex_faces[vertex as usize].len() > 1
+        {
+            0.75 * position
+                + 0.125
+                    * (self.positions[sharp[0].0 as usize] + self.positions[sharp[1].0 as usize])
+        } else {
+            // Corner
+            position
+        };
+
+        let sharpness =
+            sharp.iter().map(|(_, sharpness)| sharpness).sum::<f32>() / sharp.len() as f32;
+
+        smooth.lerp(sharp_position, sharpness.min(1.0))
+    }
+
+    /// Moves an edge vertex to the middle of the edge if the edge is sharp.
+    fn crease_edge(&self, adjacency: &Adjacency, a: u32, b: u32, smooth: Vec3A) -> Vec3A {
+        let sharpness = self.sharpness(adjacency, a, b);
+        let middle = 0.5 * (self.positions[a as usize] + self.positions[b as usize]);
+
+        smooth.lerp(middle, sharpness.min(1.0))
+    }
+
+    /// Creases of the refined mesh, every creased edge is split in two edges one level less
+    /// sharp.
+    fn refined_creases(&self, adjacency: &Adjacency) -> HashMap<(u32, u32), f32> {
+        let mut creases = HashMap::new();
+
+        for (&(a, b), &sharpness) in &self.creases {
+            let Some(edge) = adjacency.edges.get(&(a, b)) else {
+                continue;
+            };
+
+            if sharpness > 1.0 {
+                creases.insert(edge_key(a, edge.point), sharpness - 1.0);
+                creases.insert(edge_key(edge.point, b), sharpness - 1.0);
+            }
+        }
+
+        creases
+    }
+
+    fn loop_step(&self) -> PolyMesh {
+        let vertex_count = self.positions.len() as u32;
+        let adjacency = self.adjacency(vertex_count);
+
+        let mut positions = vec![Vec3A::ZERO; self.positions.len() + adjacency.edges.len()];
+
+        for vertex in 0..vertex_count {
+            let neighbours = &adjacency.neighbours[vertex as usize];
+            let n = neighbours.len() as f32;
+            let beta = if neighbours.len() == 3 {
+                3.0 / 16.0
+            } else {
+                3.0 / (8.0 * n)
+            };
+
+            let sum = neighbours
+                .iter()
+                .map(|&neighbour| self.positions[neighbour as usize])
+                .fold(Vec3A::ZERO, |sum, p| sum + p);
+            let smooth = (1.0 - n * beta) * self.positions[vertex as usize] + beta * sum;
+
+            positions[vertex as usize] = self.crease_vertex(&adjacency, vertex, smooth);
+        }
+
+        for (&(a, b), edge) in &adjacency.edges {
+            let smooth = if edge.faces.len() == 2 {
+                // Weighted with the vertices opposite of the edge in both triangles
+                let opposite = edge
+                    .faces
+                    .iter()
+                    .map(|&face| {
+                        let vertex = self.faces[face]
+                            .iter()
+                            .find(|&&v| v != a && v != b)
+                            .unwrap();
+                        self.positions[*vertex as usize]
+                    })
+                    .fold(Vec3A::ZERO, |sum, p| sum + p);
+
+                0.375 * (self.positions[a as usize] + self.positions[b as usize]) + 0.125 * opposite
+            } else {
+                0.5 * (self.positions[a as usize] + self.positions[b as usize])
+            };
+
+            positions[edge.point as usize] = self.crease_edge(&adjacency, a, b, smooth);
+        }
+
+        let mut faces = Vec::with_capacity(self.faces.len() * 4);
+        let mut face_uvs = Vec::with_capacity(self.face_uvs.len() * 4);
+
+        for (index, face) in self.faces.iter().enumerate() {
+            let [a, b, c] = [face[0], face[1], face[2]];
+            let point = |x: u32, y: u32| adjacency.edges[&edge_key(x, y)].point;
+            let (ab, bc, ca) = (point(a, b), point(b, c), point(c, a));
+
+            faces.push(vec![a, ab, ca]);
+            faces.push(vec![b, bc, ab]);
+            faces.push(vec![c, ca, bc]);
+            faces.push(vec![ab, bc, ca]);
+
+            if let Some(uvs) = self.face_uvs.get(index) {
+                let (uv_ab, uv_bc, uv_ca) = (
+                    uvs[0].lerp(uvs[1], 0.5),
+                    uvs[1].lerp(uvs[2], 0.5),
+                    uvs[2].lerp(uvs[0], 0.5),
+                );
+
+                face_uvs.push(vec![uvs[0], uv_ab, uv_ca]);
+                face_uvs.push(vec![uvs[1], uv_bc, uv_ab]);
+                face_uvs.push(vec![uvs[2], uv_ca, uv_bc]);
+                face_uvs.push(vec![uv_ab, uv_bc, uv_ca]);
+            }
+        }
+
+        PolyMesh {
+            positions,
+            faces,
+            face_uvs,
+            creases: self.refined_creases(&adjacency),
+        }
+    }
+
+    fn catmull_clark_step(&self) -> PolyMesh {
+        let vertex_count = self.positions.len();
+        let adjacency = self.adjacency(vertex_count as u32);
+        let first_face_point = vertex_count + adjacency.edges.len();
+
+        let face_points = self
+            .faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .map(|&vertex| self.positions[vertex as usize])
+                    .fold(Vec3A::ZERO, |sum, p| sum + p)
+                    / face.len() as f32
+            })
+            .collect::<Vec<_>>();
+
+        let mut positions = vec![Vec3A::ZERO; first_face_point];
+
+        for (vertex, &position) in self.positions.iter().enumerate() {
+            let faces = &adjacency.vertex_faces[vertex];
+            let neighbours = &adjacency.neighbours[vertex];
+            let n = neighbours.len() as f32;
+
+            let face_average = faces
+                .iter()
+                .map(|&face| face_points[face])
+                .fold(Vec3A::ZERO, |sum, p| sum + p)
+                / faces.len() as f32;
+            let edge_average = neighbours
+                .iter()
+                .map(|&neighbour| 0.5 * (position + self.positions[neighbour as usize]))
+                .fold(Vec3A::ZERO, |sum, p| sum + p)
+                / n;
+            let smooth = (face_average + 2.0 * edge_average + (n - 3.0) * position) / n;
+
+            positions[vertex] = self.crease_vertex(&adjacency, vertex as u32, smooth);
+        }
+
+        for (&(a, b), edge) in &adjacency.edges {
+            let ends = self.positions[a as usize] + self.positions[b as usize];
+            let smooth = if edge.faces.len() == 2 {
+                (ends + face_points[edge.faces[0]] + face_points[edge.faces[1]]) / 4.0
+            } else {
+                0.5 * ends
+            };
+
+            positions[edge.point as usize] = self.crease_edge(&adjacency, a, b, smooth);
+        }
+
+        positions.extend(&face_points);
+
+        let mut faces = vec![];
+        let mut face_uvs = vec![];
+
+        for (index, face) in self.faces.iter().enumerate() {
+            let face_point = (first_face_point + index) as u32;
+            let k = face.len();
+            let point = |corner: usize| {
+                adjacency.edges[&edge_key(face[corner], face[(corner + 1) % k])].point
+            };
+
+            for (corner, &vertex) in face.iter().enumerate() {
+                let previous = (corner + k - 1) % k;
+                faces.push(vec![vertex, point(corner), face_point, point(previous)]);
+            }
+
+            if let Some(uvs) = self.face_uvs.get(index) {
+                let center = uvs.iter().fold(Vec2::ZERO, |sum, uv| sum + *uv) / k as f32;
+                let middle = |corner: usize| uvs[corner].lerp(uvs[(corner + 1) % k], 0.5);
+
+                for (corner, &uv) in uvs.iter().enumerate() {
+                    let previous = (corner + k - 1) % k;
+                    face_uvs.push(vec![uv, middle(corner), center, middle(previous)]);
+                }
+            }
+        }
+
+        PolyMesh {
+            positions,
+            faces,
+            face_uvs,
+            creases: self.refined_creases(&adjacency),
+        }
+    }
+
+    /// Triangulates the faces into a mesh with smooth vertex normals, averaged over the faces
+    /// around each vertex. Vertices are only duplicated where texture coordinates differ.
+    pub fn to_triangle_mesh(&self, material: Arc<dyn Material>) -> TriangleMesh {
+        let mut vertex_normals = vec![Vec3A::ZERO; self.positions.len()];
+        for face in &self.faces {
+            for corner in 1..face.len() - 1 {
+                let (a, b, c) = (face[0], face[corner], face[corner + 1]);
+                // Weighted by area through the length of the cross product
+                let normal = (self.positions[b as usize] - self.positions[a as usize])
+                    .cross(self.positions[c as usize] - self.positions[a as usize]);
+
+                for vertex in [a, b, c] {
+                    vertex_normals[vertex as usize] += normal;
+                }
+            }
+        }
+
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        let mut indices = vec![];
+        let mut vertices: HashMap<(u32, [u32; 2]), u32> = HashMap::new();
+
+        for (index, face) in self.faces.iter().enumerate() {
+            let mut corner_index = |corner: usize| {
+                let vertex = face[corner];
+                let uv = self
+                    .face_uvs
+                    .get(index)
+                    .map_or(Vec2::ZERO, |face_uvs| face_uvs[corner]);
+
+                *vertices
+                    .entry((vertex, [uv.x.to_bits(), uv.y.to_bits()]))
+                    .or_insert_with(|| {
+                        positions.push(self.positions[vertex as usize]);
+                        normals.push(vertex_normals[vertex as usize].normalize_or_zero());
+                        uvs.push(uv);
+                        positions.len() as u32 - 1
+                    })
+            };
+
+            let first = corner_index(0);
+            for corner in 1..face.len() - 1 {
+                indices.push([first, corner_index(corner), corner_index(corner + 1)]);
+            }
+        }
+
+        if self.face_uvs.is_empty() {
+            uvs.clear();
+        }
+
+        TriangleMesh::new(positions, normals, uvs, indices, material)
+    }
+}
@@ -1,9 +1,45 @@
 #![feature(test)]
 
-use glam::Vec3A;
-use rand::Rng;
+use std::sync::Arc;
 
-use crate::{camera::Camera, hittable::Hittable, ray::Ray, scene::Scene};
+use glam::{Affine3A, Quat, Vec2, Vec3, Vec3A};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use crate::{
+    aabb::AABB,
+    animation::{AnimatedObject, Animation, CameraAnimation, Interpolation, Keyframe, Track},
+    axis::Axis,
+    bvh::sah::BVH,
+    camera::Camera,
+    csg::{Csg, CsgOp},
+    curve::{Curve, CurveType},
+    displacement::displace,
+    geometry::{
+        AxisRect, Cone, Cuboid, Cylinder, Disk, MovingSphere, Paraboloid, Plane, Quad, Sphere,
+        Torus, Triangle,
+    },
+    heightfield::Heightfield,
+    hittable::Hittable,
+    instance::{AnimatedInstance, Instance, TransformKeyframe},
+    material::{Dialectric, Dispersion, Lambertian, Material, Metal, RoughDialectric},
+    medium::{
+        ConstantMedium, HomogeneousMedium, Isotropic, Medium, MediumInterface, MediumSample,
+        WithMedia,
+    },
+    mesh::TriangleMesh,
+    microfacet::{fresnel_dielectric, TrowbridgeReitz},
+    next_medium,
+    patch::load_patches,
+    ray::{HitRecord, Ray},
+    scene::Scene,
+    sdf::{Mandelbulb, Sdf, SdfObject, SdfSphere},
+    spectrum::SampledWavelengths,
+    subdivision::PolyMesh,
+    texture::{color::SolidColor, Texture},
+    transform::{Rotate, Scale, Translate},
+    util::solve_quartic,
+    volume::{HeterogeneousMedium, VoxelGrid},
+};
 
 /// Creates `n` deterministic random cubes. Returns the `Vec` of surface `Triangle`s.
 
@@ -50,3 +86,1550 @@ fn create_scene_and_bvh(b: &mut ::test::Bencher) {
         traverse(bvh, &scene.objects, b);
     });
 }
+
+/// Plain white diffuse material for tests that only look at geometry.
+fn white() -> Arc<dyn Material> {
+    Arc::new(Lambertian {
+        albedo: Box::new(SolidColor::new(1.0, 1.0, 1.0)),
+    })
+}
+
+fn unit_triangle() -> Triangle {
+    Triangle::new(
+        Vec3A::new(0.0, 0.0, 0.0),
+        Vec3A::new(1.0, 0.0, 0.0),
+        Vec3A::new(0.0, 1.0, 0.0),
+        white(),
+    )
+}
+
+fn ray(origin: Vec3A, direction: Vec3A) -> Ray {
+    Ray {
+        origin,
+        direction,
+        time: 0.0,
+    }
+}
+
+#[test]
+fn triangle_hit_reports_point_and_normal() {
+    let triangle = unit_triangle();
+    let r = ray(Vec3A::new(0.25, 0.25, 5.0), Vec3A::new(0.0, 0.0, -2.0));
+
+    let rec = triangle.hit(&r, 0.001, f32::MAX).unwrap();
+
+    assert!((rec.t - 2.5).abs() < 1e-6);
+    assert!((rec.p - Vec3A::new(0.25, 0.25, 0.0)).length() < 1e-6);
+    assert!((rec.normal - Vec3A::new(0.0, 0.0, 1.0)).length() < 1e-6);
+    assert!(rec.front_face);
+    assert!((rec.barycentric - Vec2::new(0.25, 0.25)).length() < 1e-6);
+}
+
+#[test]
+fn triangle_interpolates_vertex_uvs() {
+    let mut triangle = unit_triangle();
+    (triangle.uv0, triangle.uv1, triangle.uv2) = (
+        Vec2::new(0.5, 0.5),
+        Vec2::new(1.0, 0.5),
+        Vec2::new(0.5, 0.0),
+    );
+    let mesh = TriangleMesh::new(
+        vec![triangle.vertex0, triangle.vertex1, triangle.vertex2],
+        vec![],
+        vec![triangle.uv0, triangle.uv1, triangle.uv2],
+        vec![[0, 1, 2]],
+        white(),
+    );
+
+    // A quarter of the way to vertex1 and half of the way to vertex2
+    let r = ray(Vec3A::new(0.25, 0.5, 1.0), Vec3A::new(0.0, 0.0, -1.0));
+    for rec in [
+        triangle.hit(&r, 0.001, f32::MAX).unwrap(),
+        mesh.hit(&r, 0.001, f32::MAX).unwrap(),
+    ] {
+        assert!((rec.barycentric - Vec2::new(0.25, 0.5)).length() < 1e-6);
+        assert!((Vec2::new(rec.u, rec.v) - Vec2::new(0.625, 0.25)).length() < 1e-6);
+    }
+}
+
+#[test]
+fn triangle_hit_honours_t_interval() {
+    let triangle = unit_triangle();
+    let r = ray(Vec3A::new(0.25, 0.25, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+
+    assert!(triangle.hit(&r, 0.001, 4.9).is_none());
+    assert!(triangle.hit(&r, 5.1, f32::MAX).is_none());
+    assert!(triangle.hit(&r, 4.9, 5.1).is_some());
+
+    // Triangle behind the origin
+    let r = ray(Vec3A::new(0.25, 0.25, 5.0), Vec3A::new(0.0, 0.0, 1.0));
+    assert!(triangle.hit(&r, 0.001, f32::MAX).is_none());
+}
+
+#[test]
+fn triangle_back_face_hit_and_culling() {
+    let mut triangle = unit_triangle();
+    let r = ray(Vec3A::new(0.25, 0.25, -1.0), Vec3A::new(0.0, 0.0, 1.0));
+
+    let rec = triangle.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!(!rec.front_face);
+    assert!((rec.normal - Vec3A::new(0.0, 0.0, -1.0)).length() < 1e-6);
+    assert!((rec.t - 1.0).abs() < 1e-6);
+
+    triangle.cull_backfaces = true;
+    assert!(triangle.hit(&r, 0.001, f32::MAX).is_none());
+}
+
+#[test]
+fn triangle_miss_outside_edges() {
+    let triangle = unit_triangle();
+
+    for origin in [
+        Vec3A::new(0.6, 0.6, 1.0),
+        Vec3A::new(-0.1, 0.5, 1.0),
+        Vec3A::new(0.5, -0.1, 1.0),
+    ] {
+        let r = ray(origin, Vec3A::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&r, 0.001, f32::MAX).is_none());
+    }
+
+    // Parallel to the triangle plane
+    let r = ray(Vec3A::new(-1.0, 0.25, 0.0), Vec3A::new(1.0, 0.0, 0.0));
+    assert!(triangle.hit(&r, 0.001, f32::MAX).is_none());
+}
+
+#[test]
+fn triangle_shared_edge_is_watertight() {
+    let material = white();
+    let a = Vec3A::new(0.0, 0.0, 0.0);
+    let b = Vec3A::new(1.0, 0.0, 0.0);
+    let c = Vec3A::new(1.0, 1.0, 0.0);
+    let d = Vec3A::new(0.0, 1.0, 0.0);
+    let lower = Triangle::new(a, b, c, material.clone());
+    let upper = Triangle::new(a, c, d, material);
+
+    let mut rng = SmallRng::seed_from_u64(7);
+    for _ in 0..1000 {
+        // Points on the shared diagonal, seen from random directions
+        let s: f32 = rng.gen();
+        let target = Vec3A::new(s, s, 0.0);
+        let origin = Vec3A::new(
+            rng.gen_range(-2.0..2.0),
+            rng.gen_range(-2.0..2.0),
+            rng.gen_range(0.5..3.0),
+        );
+        let r = ray(origin, target - origin);
+
+        assert!(
+            lower.hit(&r, 0.001, f32::MAX).is_some() || upper.hit(&r, 0.001, f32::MAX).is_some()
+        );
+    }
+}
+
+#[test]
+fn quartic_roots() {
+    // (x - 1)(x + 2)(x - 3)(x + 0.5)
+    let mut roots = solve_quartic(1.0, -1.5, -6.0, 3.5, 3.0);
+    roots.sort_by(f64::total_cmp);
+
+    assert_eq!(roots.len(), 4);
+    for (root, expected) in roots.iter().zip([-2.0, -0.5, 1.0, 3.0]) {
+        assert!((root - expected).abs() < 1e-9);
+    }
+
+    // x^4 + 1 has no real roots
+    assert!(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0).is_empty());
+}
+
+#[test]
+fn torus_hit_through_hole() {
+    let torus = Torus {
+        center: Vec3A::new(0.0, 1.0, 0.0),
+        major_radius: 2.0,
+        minor_radius: 0.5,
+        material: white(),
+    };
+
+    // Along the x axis the ray crosses the tube twice, entering at x = -2.5
+    let r = ray(Vec3A::new(-5.0, 1.0, 0.0), Vec3A::new(1.0, 0.0, 0.0));
+    let rec = torus.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 2.5).abs() < 1e-4);
+    assert!((rec.normal - Vec3A::new(-1.0, 0.0, 0.0)).length() < 1e-4);
+    assert!(rec.front_face);
+
+    // Leaving the first tube at x = -1.5, and the hole is empty up to the second one
+    let rec = torus.hit(&r, 2.6, f32::MAX).unwrap();
+    assert!((rec.t - 3.5).abs() < 1e-4);
+    assert!(!rec.front_face);
+    assert!(torus.hit(&r, 3.6, 6.4).is_none());
+
+    // Straight down through the hole
+    let r = ray(Vec3A::new(0.0, 5.0, 0.0), Vec3A::new(0.0, -1.0, 0.0));
+    assert!(torus.hit(&r, 0.001, f32::MAX).is_none());
+}
+
+fn sphere(position: Vec3A, radius: f32) -> Sphere {
+    Sphere {
+        position,
+        radius,
+        material: white(),
+    }
+}
+
+#[test]
+fn csg_intersection_keeps_overlap() {
+    let lens = Csg::new(
+        CsgOp::Intersection,
+        sphere(Vec3A::new(0.0, 0.0, -1.0), 2.0),
+        sphere(Vec3A::new(0.0, 0.0, 1.0), 2.0),
+    );
+    let r = ray(Vec3A::new(0.0, 0.0, 10.0), Vec3A::new(0.0, 0.0, -1.0));
+
+    // Enters through the far sphere's near side at z = 1 and leaves at z = -1
+    let hits = lens.hit_all(&r, 0.001, f32::MAX);
+    assert_eq!(hits.len(), 2);
+    assert!((hits[0].t - 9.0).abs() < 1e-4 && hits[0].front_face);
+    assert!((hits[1].t - 11.0).abs() < 1e-4 && !hits[1].front_face);
+
+    // Starting inside the lens, only the exit remains
+    let rec = lens.hit(&r, 10.0, f32::MAX).unwrap();
+    assert!((rec.t - 11.0).abs() < 1e-4 && !rec.front_face);
+
+    // Inside one sphere but outside the other
+    let r = ray(Vec3A::new(0.0, 1.9, 10.0), Vec3A::new(0.0, 0.0, -1.0));
+    assert!(lens.hit(&r, 0.001, f32::MAX).is_none());
+}
+
+#[test]
+fn csg_difference_cuts_out_right() {
+    let shell = Csg::new(
+        CsgOp::Difference,
+        sphere(Vec3A::ZERO, 2.0),
+        sphere(Vec3A::ZERO, 1.0),
+    );
+    let r = ray(Vec3A::new(0.0, 0.0, 10.0), Vec3A::new(0.0, 0.0, -1.0));
+
+    let hits = shell.hit_all(&r, 0.001, f32::MAX);
+    let expected = [(8.0, true), (9.0, false), (11.0, true), (12.0, false)];
+    assert_eq!(hits.len(), expected.len());
+    for (rec, (t, front_face)) in hits.iter().zip(expected) {
+        assert!((rec.t - t).abs() < 1e-4);
+        assert_eq!(rec.front_face, front_face);
+        // Normals keep facing the ray
+        assert!(rec.normal.dot(r.direction) < 0.0);
+    }
+}
+
+#[test]
+fn translate_moves_hits_and_bounds() {
+    let moved = Translate::new(sphere(Vec3A::ZERO, 1.0), Vec3A::new(2.0, 0.0, 0.0));
+
+    let rec = moved
+        .hit(
+            &ray(Vec3A::new(2.0, 0.0, 5.0), Vec3A::new(0.0, 0.0, -1.0)),
+            0.001,
+            f32::MAX,
+        )
+        .unwrap();
+    assert!((rec.t - 4.0).abs() < 1e-5);
+    assert!((rec.p - Vec3A::new(2.0, 0.0, 1.0)).length() < 1e-5);
+    assert!((rec.normal - Vec3A::Z).length() < 1e-5);
+
+    let aabb = moved.bounding_box();
+    assert!((aabb.minimum - Vec3A::new(1.0, -1.0, -1.0)).length() < 1e-5);
+    assert!((aabb.maximum - Vec3A::new(3.0, 1.0, 1.0)).length() < 1e-5);
+}
+
+#[test]
+fn rotate_turns_hits_and_bounds() {
+    // A quarter turn around y takes the sphere from +x to -z
+    let turned = Rotate::new(sphere(Vec3A::X, 0.5), Vec3A::Y, 90.0);
+
+    let rec = turned
+        .hit(
+            &ray(Vec3A::new(0.0, 0.0, -5.0), Vec3A::new(0.0, 0.0, 1.0)),
+            0.001,
+            f32::MAX,
+        )
+        .unwrap();
+    assert!((rec.t - 3.5).abs() < 1e-5);
+    assert!((rec.p - Vec3A::new(0.0, 0.0, -1.5)).length() < 1e-5);
+    assert!((rec.normal + Vec3A::Z).length() < 1e-5);
+
+    let aabb = turned.bounding_box();
+    assert!((aabb.minimum - Vec3A::new(-0.5, -0.5, -1.5)).length() < 1e-5);
+    assert!((aabb.maximum - Vec3A::new(0.5, 0.5, -0.5)).length() < 1e-5);
+}
+
+#[test]
+fn scale_stretches_hits_normals_and_bounds() {
+    // Stretched along x into an ellipsoid, whose normals are no longer radial
+    let stretched = Scale::new(sphere(Vec3A::ZERO, 1.0), Vec3A::new(2.0, 1.0, 1.0));
+
+    let x = 2.0f32.sqrt();
+    let rec = stretched
+        .hit(
+            &ray(Vec3A::new(x, 0.0, 5.0), Vec3A::new(0.0, 0.0, -1.0)),
+            0.001,
+            f32::MAX,
+        )
+        .unwrap();
+    let z = 0.5f32.sqrt();
+    assert!((rec.t - (5.0 - z)).abs() < 1e-5);
+    assert!((rec.p - Vec3A::new(x, 0.0, z)).length() < 1e-5);
+    assert!((rec.normal - Vec3A::new(x / 4.0, 0.0, z).normalize()).length() < 1e-5);
+
+    let aabb = stretched.bounding_box();
+    assert!((aabb.minimum - Vec3A::new(-2.0, -1.0, -1.0)).length() < 1e-5);
+    assert!((aabb.maximum - Vec3A::new(2.0, 1.0, 1.0)).length() < 1e-5);
+}
+
+#[test]
+#[should_panic(expected = "flattens")]
+fn scale_rejects_zero_component() {
+    Scale::new(sphere(Vec3A::ZERO, 1.0), Vec3A::new(1.0, 0.0, 1.0));
+}
+
+#[test]
+fn transformed_csg_keeps_thin_shells() {
+    // Thinner than the step the default `hit_all` takes past every hit
+    let shell = || {
+        Csg::new(
+            CsgOp::Difference,
+            sphere(Vec3A::ZERO, 1.0),
+            sphere(Vec3A::ZERO, 0.99998),
+        )
+    };
+    let r = ray(Vec3A::new(3.0, 0.0, 10.0), Vec3A::new(0.0, 0.0, -1.0));
+    let offset = Vec3A::new(3.0, 0.0, 0.0);
+
+    let translated = Translate::new(shell(), offset);
+    let rotated = Translate::new(Rotate::new(shell(), Vec3A::Y, 30.0), offset);
+    let instance = Instance::new(Arc::new(shell()), Affine3A::from_translation(offset.into()));
+
+    for hits in [
+        translated.hit_all(&r, 0.001, f32::MAX),
+        rotated.hit_all(&r, 0.001, f32::MAX),
+        instance.hit_all(&r, 0.001, f32::MAX),
+    ] {
+        let front_faces = hits.iter().map(|rec| rec.front_face).collect::<Vec<_>>();
+        assert_eq!(front_faces, [true, false, true, false]);
+        assert!((hits[0].p - Vec3A::new(3.0, 0.0, 1.0)).length() < 1e-4);
+    }
+}
+
+#[test]
+fn sdf_sphere_matches_analytic_sphere() {
+    let sdf = SdfObject::new(
+        SdfSphere {
+            center: Vec3A::new(0.0, 1.0, 0.0),
+            radius: 1.0,
+        },
+        white(),
+    );
+    let analytic = sphere(Vec3A::new(0.0, 1.0, 0.0), 1.0);
+
+    let mut rng = SmallRng::seed_from_u64(3);
+    for _ in 0..100 {
+        let origin = Vec3A::new(
+            rng.gen_range(-5.0..5.0),
+            rng.gen_range(-5.0..5.0),
+            rng.gen_range(3.0..5.0),
+        );
+        let target = Vec3A::new(rng.gen_range(-0.7..0.7), rng.gen_range(0.3..1.7), 0.0);
+        let r = ray(origin, target - origin);
+
+        let expected = analytic.hit(&r, 0.001, f32::MAX).unwrap();
+        let rec = sdf.hit(&r, 0.001, f32::MAX).unwrap();
+        assert!((rec.p - expected.p).length() < 1e-3);
+        assert!((rec.normal - expected.normal).length() < 1e-2);
+        assert!(rec.front_face);
+
+        // Leaving the surface again, the ray only finds the far side when sent inwards
+        let outwards = ray(rec.p, rec.normal);
+        assert!(sdf.hit(&outwards, 0.001, f32::MAX).is_none());
+        let inwards = ray(rec.p, -rec.normal);
+        assert!(!sdf.hit(&inwards, 0.001, f32::MAX).unwrap().front_face);
+    }
+}
+
+#[test]
+fn mandelbulb_distance_at_center_is_zero() {
+    let bulb = Mandelbulb {
+        center: Vec3A::new(0.0, 1.0, 0.0),
+        scale: 1.0,
+        power: 8.0,
+        iterations: 12,
+    };
+
+    assert_eq!(bulb.distance(bulb.center), 0.0);
+    assert!(bulb.distance(Vec3A::new(0.0, 1.0, 3.0)) > 0.0);
+}
+
+#[test]
+fn curve_hit_across_width() {
+    let curve = Curve::new(
+        [
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(0.0, 1.0, 0.0),
+            Vec3A::new(0.0, 2.0, 0.0),
+            Vec3A::new(0.0, 3.0, 0.0),
+        ],
+        [0.2, 0.2],
+        CurveType::Round,
+        white(),
+    );
+
+    // Through the middle, the ray hits the front of the tube and its normal faces the ray
+    let r = ray(Vec3A::new(0.0, 1.5, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+    let rec = curve.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 4.9).abs() < 1e-4);
+    assert!((rec.u - 0.5).abs() < 1e-3);
+    assert!((rec.v - 0.5).abs() < 1e-3);
+    assert!((rec.normal - Vec3A::Z).length() < 1e-3);
+
+    // Near the edge the ray hits further back, where the tube surface turns sideways
+    let r = ray(Vec3A::new(0.09, 1.5, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+    let rec = curve.hit(&r, 0.001, f32::MAX).unwrap();
+    let z = (0.1f32 * 0.1 - 0.09 * 0.09).sqrt();
+    assert!((rec.t - (5.0 - z)).abs() < 1e-4);
+    assert!((rec.p - Vec3A::new(0.09, 1.5, z)).length() < 1e-4);
+    assert!((rec.normal - Vec3A::new(0.9, 0.0, z / 0.1)).length() < 1e-3);
+
+    // Along a slanted ray the tube is hit where its surface is, not at the axis
+    let r = ray(Vec3A::new(-5.0, 1.5, 5.0), Vec3A::new(1.0, 0.0, -1.0));
+    let rec = curve.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.p.length_squared() - 1.5 * 1.5 - 0.01).abs() < 1e-3);
+    assert!((rec.normal - Vec3A::new(-1.0, 0.0, 1.0).normalize()).length() < 1e-3);
+
+    // Past the width and beyond the ends
+    let r = ray(Vec3A::new(0.11, 1.5, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+    assert!(curve.hit(&r, 0.001, f32::MAX).is_none());
+    let r = ray(Vec3A::new(0.0, 3.2, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+    assert!(curve.hit(&r, 0.001, f32::MAX).is_none());
+
+    // Split segments together cover the same curve
+    let segments = curve.split(3);
+    let r = ray(Vec3A::new(0.0, 2.5, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+    let hits = segments
+        .iter()
+        .filter_map(|segment| segment.hit(&r, 0.001, f32::MAX))
+        .collect::<Vec<_>>();
+    assert_eq!(hits.len(), 1);
+    assert!((hits[0].u - 2.5 / 3.0).abs() < 1e-3);
+}
+
+#[test]
+fn patch_loads_and_hits_like_its_tessellation() {
+    // A single dome-shaped patch in the teapot format, with one-based indices
+    let mut contents = String::from("1\n");
+    contents += &(1..=16)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    contents += "\n16\n";
+    for row in 0..4 {
+        for column in 0..4 {
+            let height = if (1..3).contains(&row) && (1..3).contains(&column) {
+                1.0
+            } else {
+                0.0
+            };
+            contents += &format!("{}, {}, {}\n", column, row, height);
+        }
+    }
+    let path = std::env::temp_dir().join("raytrace_test_patch.bpt");
+    std::fs::write(&path, contents).unwrap();
+
+    let material = white();
+    let patches = load_patches(path.to_str().unwrap(), material, |p| p).unwrap();
+    assert_eq!(patches.len(), 1);
+
+    let patch = &patches[0];
+    let mesh = patch.tessellate(64);
+
+    let mut rng = SmallRng::seed_from_u64(5);
+    for _ in 0..100 {
+        let target = Vec3A::new(rng.gen_range(0.2..2.8), rng.gen_range(0.2..2.8), 0.0);
+        let r = ray(
+            target + Vec3A::new(0.3, -0.2, 5.0),
+            Vec3A::new(-0.3, 0.2, -5.0),
+        );
+
+        let rec = patch.hit(&r, 0.001, f32::MAX).unwrap();
+        let expected = mesh.hit(&r, 0.001, f32::MAX).unwrap();
+        assert!((rec.p - expected.p).length() < 1e-2);
+        assert!(rec.normal.dot(expected.normal) > 0.99);
+
+        let (p, _, _) = patch.evaluate(rec.u, rec.v);
+        assert!((p - rec.p).length() < 1e-3);
+    }
+
+    // Past the sides of the patch
+    let r = ray(Vec3A::new(3.5, 1.5, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+    assert!(patch.hit(&r, 0.001, f32::MAX).is_none());
+}
+
+#[test]
+fn subdivision_keeps_boundaries_and_shrinks_cage() {
+    // A single open quad stays flat and keeps its corners
+    let quad = PolyMesh {
+        positions: vec![
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(1.0, 0.0, 0.0),
+            Vec3A::new(1.0, 0.0, 1.0),
+            Vec3A::new(0.0, 0.0, 1.0),
+        ],
+        faces: vec![vec![0, 1, 2, 3]],
+        ..Default::default()
+    }
+    .subdivide(2);
+    assert_eq!(quad.faces.len(), 16);
+    assert!(quad.positions.iter().all(|p| p.y == 0.0));
+    assert_eq!(
+        quad.positions[..4],
+        [
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(1.0, 0.0, 0.0),
+            Vec3A::new(1.0, 0.0, 1.0),
+            Vec3A::new(0.0, 0.0, 1.0),
+        ]
+    );
+
+    // A closed tetrahedron shrinks inside its cage under Loop subdivision
+    let tetrahedron = PolyMesh {
+        positions: vec![
+            Vec3A::new(1.0, 1.0, 1.0),
+            Vec3A::new(1.0, -1.0, -1.0),
+            Vec3A::new(-1.0, 1.0, -1.0),
+            Vec3A::new(-1.0, -1.0, 1.0),
+        ],
+        faces: vec![vec![0, 1, 2], vec![0, 3, 1], vec![0, 2, 3], vec![1, 3, 2]],
+        ..Default::default()
+    };
+    let smooth = tetrahedron.subdivide(3);
+    assert_eq!(smooth.faces.len(), 4 * 64);
+    assert!(smooth
+        .positions
+        .iter()
+        .all(|p| p.length() < 3f32.sqrt() - 0.1));
+
+    // Unless its edges are creased
+    let creased = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]
+        .iter()
+        .fold(tetrahedron, |mesh, &(a, b)| {
+            mesh.with_crease(a, b, f32::INFINITY)
+        })
+        .subdivide(3);
+    assert_eq!(creased.positions[0], Vec3A::new(1.0, 1.0, 1.0));
+
+    let mesh = smooth.to_triangle_mesh(white());
+    let r = ray(Vec3A::new(0.0, 0.0, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+    let rec = mesh.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!(rec.normal.z > 0.9);
+}
+
+#[test]
+fn displacement_tessellates_without_cracks() {
+    let material = white();
+    let square = TriangleMesh::new(
+        vec![
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(1.0, 0.0, 0.0),
+            Vec3A::new(1.0, 0.0, 1.0),
+            Vec3A::new(0.0, 0.0, 1.0),
+        ],
+        vec![],
+        vec![],
+        vec![[0, 2, 1], [0, 3, 2]],
+        material,
+    );
+
+    let mesh = displace(&square, &SolidColor::new(0.5, 0.5, 0.5), 2.0, 0.1);
+    assert!(mesh.positions.iter().all(|p| (p.y - 1.0).abs() < 1e-6));
+
+    // Every edge inside the square is shared by two triangles
+    let mut edges = std::collections::HashMap::new();
+    for triangle in &mesh.indices {
+        for i in 0..3 {
+            let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+            let length = (mesh.positions[a as usize] - mesh.positions[b as usize]).length();
+            assert!(length <= 0.1);
+            *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    }
+    for ((a, b), count) in edges {
+        let middle = 0.5 * (mesh.positions[a as usize] + mesh.positions[b as usize]);
+        let on_border = [middle.x, middle.z]
+            .iter()
+            .any(|x| x.abs() < 1e-6 || (x - 1.0).abs() < 1e-6);
+        assert_eq!(count, if on_border { 1 } else { 2 });
+    }
+}
+
+#[test]
+fn displacement_keeps_split_vertices_together() {
+    let material = white();
+
+    // Two triangles meeting along the diagonal, each with its own copies of the shared
+    // vertices and normals, like a hard edge or a uv seam
+    let (a, c) = (Vec3A::new(0.0, 0.0, 0.0), Vec3A::new(1.0, 0.0, 1.0));
+    let tilted = Vec3A::new(1.0, 1.0, 0.0).normalize();
+    let square = TriangleMesh::new(
+        vec![
+            a,
+            c,
+            Vec3A::new(1.0, 0.0, 0.0),
+            a,
+            Vec3A::new(0.0, 0.0, 1.0),
+            c,
+        ],
+        vec![Vec3A::Y, Vec3A::Y, Vec3A::Y, tilted, tilted, tilted],
+        vec![],
+        vec![[0, 1, 2], [3, 4, 5]],
+        material,
+    );
+
+    let mesh = displace(&square, &SolidColor::new(0.5, 0.5, 0.5), 2.0, 0.25);
+    assert!((mesh.positions[0] - mesh.positions[3]).length() < 1e-6);
+    assert!((mesh.positions[1] - mesh.positions[5]).length() < 1e-6);
+    assert!((mesh.normals[0] - mesh.normals[3]).length() < 1e-6);
+}
+
+/// Texture rising from black to white along u, for displacements that differ across uv seams.
+#[derive(Debug)]
+struct Ramp;
+
+impl Texture for Ramp {
+    fn value(&self, u: f32, _v: f32, _p: Vec3A) -> Vec3A {
+        Vec3A::splat(u)
+    }
+}
+
+#[test]
+fn displacement_closes_uv_seams() {
+    let material = white();
+
+    // The same square as two triangles meeting along the diagonal, whose copies of the shared
+    // vertices lie at opposite ends of the texture
+    let (a, c) = (Vec3A::new(0.0, 0.0, 0.0), Vec3A::new(1.0, 0.0, 1.0));
+    let square = TriangleMesh::new(
+        vec![
+            a,
+            c,
+            Vec3A::new(1.0, 0.0, 0.0),
+            a,
+            Vec3A::new(0.0, 0.0, 1.0),
+            c,
+        ],
+        vec![Vec3A::Y; 6],
+        vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.2, 1.0),
+            Vec2::new(0.1, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(0.9, 1.0),
+            Vec2::new(0.8, 1.0),
+        ],
+        vec![[0, 1, 2], [3, 4, 5]],
+        material,
+    );
+
+    let mesh = displace(&square, &Ramp, 1.0, 0.25);
+
+    // Both sides move the diagonal by the average of their heights
+    assert!((mesh.positions[0] - mesh.positions[3]).length() < 1e-6);
+    assert!((mesh.positions[0].y - 0.5).abs() < 1e-6);
+    assert!((mesh.positions[1] - mesh.positions[5]).length() < 1e-6);
+
+    // Every vertex tessellating the diagonal has a copy at the same displaced position
+    let diagonal = mesh
+        .positions
+        .iter()
+        .filter(|p| (p.x - p.z).abs() < 1e-6)
+        .collect::<Vec<_>>();
+    assert!(diagonal.len() > 4);
+    for p in &diagonal {
+        let copies = diagonal.iter().filter(|q| (**p - ***q).length() < 1e-6);
+        assert_eq!(copies.count(), 2);
+    }
+}
+
+#[test]
+fn heightfield_matches_triangle_mesh() {
+    let material = white();
+
+    let (nx, nz) = (12, 9);
+    let mut rng = SmallRng::seed_from_u64(7);
+    let heights = (0..nx * nz).map(|_| rng.gen::<f32>()).collect::<Vec<_>>();
+    let origin = Vec3A::new(-2.0, 0.5, -1.0);
+    let size = Vec3A::new(4.0, 1.5, 3.0);
+
+    let positions = (0..nx * nz)
+        .map(|k| {
+            let (i, j) = (k % nx, k / nx);
+            origin
+                + Vec3A::new(
+                    i as f32 * size.x / (nx - 1) as f32,
+                    heights[k] * size.y,
+                    j as f32 * size.z / (nz - 1) as f32,
+                )
+        })
+        .collect();
+    let indices = (0..nz - 1)
+        .flat_map(|j| (0..nx - 1).map(move |i| (i, j)))
+        .flat_map(|(i, j)| {
+            let k = |i: usize, j: usize| (j * nx + i) as u32;
+            [
+                [k(i, j), k(i, j + 1), k(i + 1, j + 1)],
+                [k(i, j), k(i + 1, j + 1), k(i + 1, j)],
+            ]
+        })
+        .collect();
+    let mesh = TriangleMesh::new(positions, vec![], vec![], indices, material.clone());
+    let heightfield = Heightfield::new(heights, nx, nz, origin, size, material);
+
+    for _ in 0..200 {
+        let from = Vec3A::new(
+            rng.gen_range(-4.0..4.0),
+            rng.gen_range(0.0..4.0),
+            rng.gen_range(-3.0..3.0),
+        );
+        let to = Vec3A::new(rng.gen_range(-2.0..2.0), 0.5, rng.gen_range(-1.0..2.0));
+        let r = ray(from, to - from);
+
+        let expected = mesh.hit(&r, 0.001, f32::MAX);
+        let rec = heightfield.hit(&r, 0.001, f32::MAX);
+        assert_eq!(rec.is_some(), expected.is_some());
+        if let (Some(rec), Some(expected)) = (rec, expected) {
+            assert!((rec.t - expected.t).abs() < 1e-4);
+        }
+    }
+}
+
+#[test]
+fn constant_medium_transmittance() {
+    let material: Arc<dyn Material> = Arc::new(Isotropic {
+        albedo: Box::new(SolidColor::new(1.0, 1.0, 1.0)),
+    });
+    let medium = ConstantMedium::new(sphere(Vec3A::ZERO, 1.0), 0.5, material);
+
+    // Through the middle the ray passes a distance of 2, starting inside only the radius
+    for (origin, distance) in [(Vec3A::new(0.0, 0.0, 5.0), 2.0), (Vec3A::ZERO, 1.0)] {
+        let r = ray(origin, Vec3A::new(0.0, 0.0, -2.0));
+        let passed = (0..20000)
+            .filter(|_| medium.hit(&r, 0.001, f32::MAX).is_none())
+            .count();
+
+        let expected = (-0.5f32 * distance).exp();
+        assert!((passed as f32 / 20000.0 - expected).abs() < 0.02);
+    }
+}
+
+#[test]
+fn voxel_grid_loads_and_tracks_density() {
+    // Density rising along x from 0 to 1 over a 4 by 2 by 2 grid
+    let dims = [4u32, 2, 2];
+    let values = (0..16).map(|i| (i % 4) as f32 / 3.0).collect::<Vec<f32>>();
+    let mut bytes = dims
+        .iter()
+        .flat_map(|d| d.to_le_bytes())
+        .collect::<Vec<u8>>();
+    bytes.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+
+    let path = std::env::temp_dir().join("raytrace_voxel_grid_test.bin");
+    std::fs::write(&path, &bytes).unwrap();
+    let grid = VoxelGrid::load(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(grid.dims, [4, 2, 2]);
+    assert_eq!(grid.data, values);
+    assert!((grid.lookup(Vec3A::new(0.5, 0.5, 0.5)) - 0.5).abs() < 1e-6);
+
+    // Truncated files are rejected
+    std::fs::write(&path, &bytes[..bytes.len() - 4]).unwrap();
+    assert!(VoxelGrid::load(path.to_str().unwrap()).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    // Along y the density is constant, so delta tracking matches Beer-Lambert
+    let bounds = AABB::new(Vec3A::ZERO, Vec3A::new(4.0, 2.0, 2.0));
+    let medium = HeterogeneousMedium::new(grid, 0.5, bounds, Vec3A::ONE, 0.0, None);
+    let r = ray(Vec3A::new(2.5, -1.0, 1.0), Vec3A::new(0.0, 1.0, 0.0));
+    // x = 2.5 lies on the center of the third voxel, with a density of 2/3
+    let expected = (-0.5 * 2.0 / 3.0 * 2.0f32).exp();
+
+    let passed = (0..20000)
+        .filter(|_| medium.hit(&r, 0.001, f32::MAX).is_none())
+        .count() as f32
+        / 20000.0;
+
+    assert!((passed - expected).abs() < 0.02);
+}
+
+#[test]
+fn homogeneous_medium_weights_match_beer_lambert() {
+    let r = ray(Vec3A::ZERO, Vec3A::new(0.0, 0.0, 2.0));
+
+    // Absorbing media always let the ray pass, weakened by the distance of 4
+    let glass = HomogeneousMedium::absorbing(Vec3A::new(0.1, 0.5, 1.0));
+    match glass.sample(&r, 2.0) {
+        MediumSample::Pass { weight } => {
+            assert!((weight - (-4.0 * Vec3A::new(0.1, 0.5, 1.0)).exp()).length() < 1e-6)
+        }
+        MediumSample::Scatter { .. } => panic!("absorbing medium scattered"),
+    }
+
+    // With scattering, the passing weights still average to the transmittance per channel
+    let sigma_s = Vec3A::new(0.1, 0.2, 0.3);
+    let fog = HomogeneousMedium::new(Vec3A::splat(0.05), sigma_s, 0.0);
+    let mut passed = Vec3A::ZERO;
+    for _ in 0..50000 {
+        if let MediumSample::Pass { weight } = fog.sample(&r, 2.0) {
+            passed += weight;
+        }
+    }
+
+    let expected = (-4.0 * (sigma_s + Vec3A::splat(0.05))).exp();
+    assert!((passed / 50000.0 - expected).abs().max_element() < 0.01);
+}
+
+#[test]
+fn tinted_medium_leaves_transmittance_at_depth() {
+    let tint = HomogeneousMedium::with_transmittance(Vec3A::new(0.5, 0.25, 1.0), 2.0);
+
+    // Travelling a distance of 4 is twice the reference depth
+    let r = ray(Vec3A::ZERO, Vec3A::new(0.0, 0.0, 2.0));
+    match tint.sample(&r, 2.0) {
+        MediumSample::Pass { weight } => {
+            assert!((weight - Vec3A::new(0.25, 0.0625, 1.0)).length() < 1e-5)
+        }
+        MediumSample::Scatter { .. } => panic!("tinted medium scattered"),
+    }
+}
+
+#[test]
+#[should_panic(expected = "positive depth")]
+fn tinted_medium_rejects_zero_depth() {
+    HomogeneousMedium::with_transmittance(Vec3A::new(0.5, 0.25, 1.0), 0.0);
+}
+
+#[test]
+fn rays_switch_media_through_surfaces() {
+    let fog: Arc<dyn Medium> = Arc::new(HomogeneousMedium::absorbing(Vec3A::splat(0.1)));
+    let glass: Arc<dyn Medium> = Arc::new(HomogeneousMedium::absorbing(Vec3A::splat(1.0)));
+
+    let mut scene = Scene::new();
+    scene.medium = Some(fog.clone());
+
+    let ball = WithMedia::new(
+        sphere(Vec3A::ZERO, 1.0),
+        MediumInterface {
+            interior: Some(glass.clone()),
+            exterior: None,
+        },
+    );
+    let is = |medium: Option<&Arc<dyn Medium>>, expected: &Arc<dyn Medium>| {
+        medium.is_some_and(|medium| Arc::ptr_eq(medium, expected))
+    };
+
+    // Refracted in from the fog, the ray travels through the glass
+    let r = ray(Vec3A::new(0.0, 0.0, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+    let rec = ball.hit(&r, 0.001, f32::MAX).unwrap();
+    let inwards = ray(rec.p, Vec3A::new(0.1, 0.0, -1.0));
+    let medium = next_medium(&scene, &rec, &inwards, Some(&fog));
+    assert!(is(medium, &glass));
+
+    // Reflected off the outside, it stays in the fog
+    let reflected = ray(rec.p, Vec3A::Z);
+    assert!(is(next_medium(&scene, &rec, &reflected, Some(&fog)), &fog));
+
+    // Leaving the far side, it is back in the medium of the scene
+    let rec = ball.hit(&inwards, 0.001, f32::MAX).unwrap();
+    assert!(!rec.front_face);
+    let outwards = ray(rec.p, inwards.direction);
+    assert!(is(next_medium(&scene, &rec, &outwards, medium), &fog));
+
+    // Reflected inside, it stays in the glass
+    let reflected = ray(rec.p, -inwards.direction);
+    assert!(is(next_medium(&scene, &rec, &reflected, medium), &glass));
+
+    // Surfaces without media leave the ray where it was
+    let rec = sphere(Vec3A::ZERO, 1.0).hit(&r, 0.001, f32::MAX).unwrap();
+    assert!(is(
+        next_medium(&scene, &rec, &inwards, Some(&glass)),
+        &glass
+    ));
+    assert!(next_medium(&scene, &rec, &inwards, None).is_none());
+}
+
+#[test]
+fn thicker_tinted_glass_transmits_less() {
+    let glass: Arc<dyn Material> =
+        Arc::new(Dialectric::new(1.5).with_transmittance(Vec3A::new(0.3, 0.7, 0.9), 0.5));
+    let scene = Scene::new();
+
+    // Light crossing a ball of the glass straight through its middle
+    let transmitted = |radius: f32| {
+        let ball = Sphere {
+            position: Vec3A::ZERO,
+            radius,
+            material: glass.clone(),
+        };
+        let r = ray(Vec3A::new(0.0, 0.0, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+        let rec = ball.hit(&r, 0.001, f32::MAX).unwrap();
+
+        let inside = ray(rec.p, r.direction);
+        let medium = next_medium(&scene, &rec, &inside, None).expect("glass has no interior");
+        let exit = ball.hit(&inside, 0.001, f32::MAX).unwrap();
+        match medium.sample(&inside, exit.t) {
+            MediumSample::Pass { weight } => weight,
+            MediumSample::Scatter { .. } => panic!("tinted glass scattered"),
+        }
+    };
+
+    // Half a unit of glass leaves the transmittance color, more of it leaves less
+    assert!((transmitted(0.25) - Vec3A::new(0.3, 0.7, 0.9)).length() < 1e-4);
+    let (thin, thick) = (transmitted(0.5), transmitted(1.0));
+    assert!(thick.cmplt(thin).all());
+    assert!((thick - Vec3A::new(0.3, 0.7, 0.9).powf(4.0)).length() < 1e-4);
+}
+
+#[test]
+fn spectral_white_and_dispersion() {
+    // White uplifts to a flat spectrum, which comes back as white averaged over the wavelengths
+    let n = 1000;
+    let mut sum = Vec3A::ZERO;
+    for i in 0..n {
+        let wavelengths = SampledWavelengths::sample((i as f32 + 0.5) / n as f32);
+        sum += wavelengths.to_rgb(wavelengths.uplift(Vec3A::ONE));
+    }
+    assert!((sum / n as f32 - Vec3A::ONE).abs().max_element() < 0.02);
+
+    // Only the hero wavelength counts once the others are dropped, for four times the weight
+    let mut wavelengths = SampledWavelengths::sample(0.3);
+    let before = wavelengths.pdf.x;
+    wavelengths.terminate_secondary();
+    assert_eq!(wavelengths.pdf.x, before / 4.0);
+    assert_eq!(wavelengths.pdf.y, 0.0);
+
+    // Blue bends more than red
+    for dispersion in [
+        Dispersion::Cauchy { a: 1.5, b: 0.004 },
+        Dispersion::Sellmeier {
+            b: [1.0396, 0.2318, 1.0105],
+            c: [0.0060, 0.0200, 103.56],
+        },
+    ] {
+        assert!(dispersion.ior(450.0) > dispersion.ior(650.0));
+    }
+    let bk7 = Dispersion::Sellmeier {
+        b: [1.0396, 0.2318, 1.0105],
+        c: [0.0060, 0.0200, 103.56],
+    };
+    assert!((bk7.ior(587.6) - 1.5168).abs() < 1e-3);
+}
+
+#[test]
+fn ggx_metal_keeps_energy() {
+    // A smooth white metal is a perfect mirror
+    let mirror: Arc<dyn Material> = Arc::new(Metal::new(Vec3A::ONE, 0.0));
+    let r = ray(Vec3A::new(-1.0, 1.0, 0.0), Vec3A::new(1.0, -1.0, 0.0));
+    let rec = HitRecord::new(&r, 1.0, Vec3A::Y, 0.0, 0.0, &mirror);
+    let (attenuation, scattered) = mirror.scatter(&r, &rec).unwrap();
+    assert_eq!(attenuation, Vec3A::ONE);
+    assert!((scattered.direction - Vec3A::new(1.0, 1.0, 0.0).normalize()).length() < 1e-5);
+
+    // Rough white metal only loses the light its facets shadow or reflect below the surface,
+    // little when smooth and about a third at an alpha of 0.5, integrated numerically
+    let albedo_at_normal_incidence = |metal: Metal| {
+        let metal: Arc<dyn Material> = Arc::new(metal);
+        let r = ray(Vec3A::Y, -Vec3A::Y);
+        let rec = HitRecord::new(&r, 1.0, Vec3A::Y, 0.0, 0.0, &metal);
+
+        let n = 20000;
+        let total = (0..n)
+            .filter_map(|_| metal.scatter(&r, &rec).ok())
+            .map(|(attenuation, scattered)| {
+                assert!(scattered.direction.dot(Vec3A::Y) > 0.0);
+                attenuation.x
+            })
+            .sum::<f32>();
+
+        total / n as f32
+    };
+    let smooth = albedo_at_normal_incidence(Metal::new(Vec3A::ONE, 0.3));
+    let rough = albedo_at_normal_incidence(Metal::new(Vec3A::ONE, 0.5f32.sqrt()));
+    assert!(smooth > 0.97, "{}", smooth);
+    assert!((rough - 0.688).abs() < 0.02, "{}", rough);
+
+    // Anisotropy stretches the distribution without changing its area
+    let brushed = TrowbridgeReitz::new(0.7, 0.5);
+    assert!(brushed.alpha_x > brushed.alpha_y);
+    assert!((brushed.alpha_x * brushed.alpha_y - 0.49 * 0.49).abs() < 1e-5);
+
+    // Visible normals face the viewer
+    let distribution = TrowbridgeReitz::new(0.5, 0.0);
+    let w = Vec3A::new(0.6, 0.0, 0.8);
+    let mut rng = SmallRng::seed_from_u64(7);
+    for _ in 0..1000 {
+        let wm = distribution.sample_visible_normal(w, (rng.gen(), rng.gen()));
+        assert!(wm.dot(w) >= 0.0 && wm.z > 0.0);
+    }
+}
+
+#[test]
+fn rough_dialectric_reflects_and_refracts() {
+    assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-6);
+    assert_eq!(fresnel_dielectric(0.1, 1.0 / 1.5), 1.0);
+
+    // Without a change of index every facet lets light straight through
+    let air: Arc<dyn Material> = Arc::new(RoughDialectric::new(1.0, 0.8));
+    let r = ray(Vec3A::Y, -Vec3A::Y);
+    let rec = HitRecord::new(&r, 1.0, Vec3A::Y, 0.0, 0.0, &air);
+    for _ in 0..100 {
+        let (attenuation, scattered) = air.scatter(&r, &rec).unwrap();
+        assert!((attenuation - Vec3A::ONE).length() < 1e-4);
+        assert!((scattered.direction + Vec3A::Y).length() < 1e-4);
+    }
+
+    // Frosted glass sends light to both sides, losing a little to shadowing
+    let glass: Arc<dyn Material> = Arc::new(RoughDialectric::new(1.5, 0.3));
+    let rec = HitRecord::new(&r, 1.0, Vec3A::Y, 0.0, 0.0, &glass);
+    let n = 20000;
+    let (mut reflected, mut refracted) = (0.0, 0.0);
+    for _ in 0..n {
+        if let Ok((attenuation, scattered)) = glass.scatter(&r, &rec) {
+            if scattered.direction.y > 0.0 {
+                reflected += attenuation.x;
+            } else {
+                refracted += attenuation.x;
+            }
+        }
+    }
+    let (reflected, refracted) = (reflected / n as f32, refracted / n as f32);
+    assert!((reflected - 0.04).abs() < 0.01, "{}", reflected);
+    assert!(reflected + refracted > 0.97 && reflected + refracted <= 1.0);
+}
+
+#[test]
+fn empty_bvh_hits_nothing() {
+    // Scenes may keep everything in `unbounded`, leaving nothing to build a hierarchy over
+    let bvh = BVH::build(&vec![]);
+    let r = ray(Vec3A::ZERO, Vec3A::Z);
+
+    assert!(bvh.traverse_indices(&r).is_empty());
+    assert!(bvh.traverse(&r, &[]).is_empty());
+}
+
+#[test]
+fn tobj_mesh_transforms_normals() {
+    let material = white();
+
+    // A triangle facing (1, 1, 0) stretched along x faces (1, 2, 0)
+    let model = tobj::Mesh {
+        positions: vec![0.0, 0.0, 0.0, 1.0, -1.0, 0.0, 0.0, 0.0, 1.0],
+        normals: [1.0, 1.0, 0.0].repeat(3),
+        indices: vec![0, 1, 2],
+        ..Default::default()
+    };
+    let transform = Affine3A::from_scale(glam::Vec3::new(2.0, 1.0, 1.0));
+    let mesh = TriangleMesh::from_tobj(&model, material.clone(), transform);
+
+    assert_eq!(mesh.positions[1], Vec3A::new(2.0, -1.0, 0.0));
+    assert!((mesh.normals[0] - Vec3A::new(1.0, 2.0, 0.0).normalize()).length() < 1e-6);
+
+    // A model without faces is an empty mesh rather than a panic
+    let empty = TriangleMesh::from_tobj(&tobj::Mesh::default(), material, Affine3A::IDENTITY);
+    assert!(empty
+        .hit(&ray(Vec3A::ZERO, Vec3A::Z), 0.0, f32::INFINITY)
+        .is_none());
+}
+
+#[test]
+fn moving_sphere_without_motion_stays_put() {
+    let material = white();
+    let sphere = MovingSphere::new(Vec3A::ZERO, Vec3A::X, 0.5, 0.5, 1.0, material);
+
+    assert_eq!(sphere.center(0.0), Vec3A::ZERO);
+    assert_eq!(sphere.center(0.5), Vec3A::ZERO);
+    assert_eq!(sphere.center(1.0), Vec3A::ZERO);
+}
+
+#[test]
+fn deforming_mesh_hit_at_interpolated_vertices() {
+    let rest = vec![
+        Vec3A::new(0.0, 0.0, 0.0),
+        Vec3A::new(1.0, 0.0, 0.0),
+        Vec3A::new(0.0, 1.0, 0.0),
+    ];
+    // Lifted unevenly along z, by 1 halfway through and by 4 at the end
+    let samples = [0.0, 1.0, 4.0]
+        .iter()
+        .map(|&z| rest.iter().map(|p| *p + Vec3A::new(0.0, 0.0, z)).collect())
+        .collect::<Vec<Vec<Vec3A>>>();
+    let mesh = TriangleMesh::new(rest, vec![], vec![], vec![[0, 1, 2]], white()).with_deformation(
+        samples.clone(),
+        0.0,
+        1.0,
+    );
+
+    let mut r = ray(Vec3A::new(0.25, 0.25, 10.0), Vec3A::new(0.0, 0.0, -1.0));
+    r.time = 0.75;
+    let rec = mesh.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 7.5).abs() < 1e-5);
+    assert!((rec.p - Vec3A::new(0.25, 0.25, 2.5)).length() < 1e-5);
+
+    let aabb = mesh.bounding_box();
+    for p in samples.iter().flatten() {
+        assert!(aabb.minimum.cmple(*p).all() && aabb.maximum.cmpge(*p).all());
+    }
+}
+
+#[test]
+#[should_panic(expected = "time1 after time0")]
+fn deforming_mesh_rejects_empty_interval() {
+    let rest = vec![Vec3A::ZERO, Vec3A::X, Vec3A::Y];
+    TriangleMesh::new(rest.clone(), vec![], vec![], vec![[0, 1, 2]], white()).with_deformation(
+        vec![rest.clone(), rest],
+        0.5,
+        0.5,
+    );
+}
+
+#[test]
+fn moving_sphere_hit_at_interpolated_center() {
+    let sphere = MovingSphere::new(
+        Vec3A::ZERO,
+        Vec3A::new(2.0, 0.0, 0.0),
+        0.0,
+        1.0,
+        0.5,
+        white(),
+    );
+
+    // Halfway through the shutter the sphere is halfway along its way
+    let mut r = ray(Vec3A::new(1.0, 0.0, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+    r.time = 0.5;
+    let rec = sphere.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 4.5).abs() < 1e-5);
+    assert!((rec.p - Vec3A::new(1.0, 0.0, 0.5)).length() < 1e-5);
+    assert!((rec.normal - Vec3A::Z).length() < 1e-5);
+
+    r.time = 0.0;
+    assert!(sphere.hit(&r, 0.001, f32::MAX).is_none());
+
+    let aabb = sphere.bounding_box();
+    assert!((aabb.minimum - Vec3A::splat(-0.5)).length() < 1e-6);
+    assert!((aabb.maximum - Vec3A::new(2.5, 0.5, 0.5)).length() < 1e-6);
+}
+
+#[test]
+fn animated_instance_hit_at_interpolated_transform() {
+    let ball: Arc<dyn Hittable> = Arc::new(sphere(Vec3A::X, 0.25));
+    let spinning = AnimatedInstance::new(
+        ball,
+        vec![
+            TransformKeyframe::new(0.0, Affine3A::IDENTITY),
+            TransformKeyframe::new(1.0, Affine3A::from_rotation_y(std::f32::consts::FRAC_PI_2)),
+        ],
+    );
+
+    // Halfway through, the ball has turned by 45 degrees from +x towards -z
+    let center = Vec3A::new(0.5f32.sqrt(), 0.0, -(0.5f32.sqrt()));
+    let mut r = ray(
+        center + Vec3A::new(0.0, 5.0, 0.0),
+        Vec3A::new(0.0, -1.0, 0.0),
+    );
+    r.time = 0.5;
+    let rec = spinning.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 4.75).abs() < 1e-4);
+    assert!((rec.p - (center + Vec3A::new(0.0, 0.25, 0.0))).length() < 1e-4);
+    assert!((rec.normal - Vec3A::Y).length() < 1e-4);
+
+    r.time = 0.0;
+    assert!(spinning.hit(&r, 0.001, f32::MAX).is_none());
+
+    // The bounds cover the ball at both ends and on the arc between them
+    let aabb = spinning.bounding_box();
+    for c in [Vec3A::X, center, Vec3A::new(0.0, 0.0, -1.0)] {
+        assert!(aabb.minimum.cmple(c - 0.25).all());
+        assert!(aabb.maximum.cmpge(c + 0.25).all());
+    }
+}
+
+#[test]
+#[should_panic(expected = "same time")]
+fn animated_instance_rejects_duplicate_keyframes() {
+    let sphere: Arc<dyn Hittable> = Arc::new(Sphere {
+        position: Vec3A::ZERO,
+        radius: 1.0,
+        material: white(),
+    });
+
+    AnimatedInstance::new(
+        sphere,
+        vec![
+            TransformKeyframe::new(0.0, Affine3A::IDENTITY),
+            TransformKeyframe::new(1.0, Affine3A::from_translation(glam::Vec3::X)),
+            TransformKeyframe::new(1.0, Affine3A::IDENTITY),
+        ],
+    );
+}
+
+#[test]
+fn quadrics_hit_with_outward_normals() {
+    let material = white();
+    let close = |a: Vec3A, b: Vec3A| (a - b).length() < 1e-5;
+
+    let cylinder = Cylinder::new(Vec3A::ZERO, 1.0, 2.0, true, material.clone());
+    let rec = cylinder
+        .hit(&ray(Vec3A::new(-3.0, 1.0, 0.0), Vec3A::X), 0.0, 10.0)
+        .unwrap();
+    assert!((rec.t - 2.0).abs() < 1e-5 && rec.front_face);
+    assert!(close(rec.normal, -Vec3A::X));
+    let rec = cylinder
+        .hit(&ray(Vec3A::new(0.5, 5.0, 0.0), -Vec3A::Y), 0.0, 10.0)
+        .unwrap();
+    assert!((rec.t - 3.0).abs() < 1e-5);
+    assert!(close(rec.normal, Vec3A::Y));
+
+    // Without caps a ray down the inside never meets the side
+    let open = Cylinder::new(Vec3A::ZERO, 1.0, 2.0, false, material.clone());
+    assert!(open
+        .hit(&ray(Vec3A::new(0.5, 5.0, 0.0), -Vec3A::Y), 0.0, 10.0)
+        .is_none());
+
+    let cone = Cone::new(Vec3A::ZERO, 1.0, 1.0, false, material.clone());
+    let rec = cone
+        .hit(&ray(Vec3A::new(-3.0, 0.5, 0.0), Vec3A::X), 0.0, 10.0)
+        .unwrap();
+    assert!((rec.t - 2.5).abs() < 1e-5);
+    assert!(close(rec.normal, Vec3A::new(-1.0, 1.0, 0.0).normalize()));
+
+    // y = x² from the outside, and the vertex from the inside
+    let paraboloid = Paraboloid::new(Vec3A::ZERO, 1.0, 1.0, false, material.clone());
+    let rec = paraboloid
+        .hit(&ray(Vec3A::new(-3.0, 0.25, 0.0), Vec3A::X), 0.0, 10.0)
+        .unwrap();
+    assert!((rec.t - 2.5).abs() < 1e-5 && rec.front_face);
+    assert!(close(rec.normal, Vec3A::new(-1.0, -1.0, 0.0).normalize()));
+    let rec = paraboloid
+        .hit(&ray(Vec3A::new(0.0, 5.0, 0.0), -Vec3A::Y), 0.0, 10.0)
+        .unwrap();
+    assert!((rec.t - 5.0).abs() < 1e-5 && !rec.front_face);
+    assert!(close(rec.normal, Vec3A::Y));
+
+    let disk = Disk::new(Vec3A::ZERO, 1.0, 0.5, material);
+    let rec = disk
+        .hit(&ray(Vec3A::new(0.75, 1.0, 0.0), -Vec3A::Y), 0.0, 10.0)
+        .unwrap();
+    assert!((rec.t - 1.0).abs() < 1e-5 && (rec.v - 0.5).abs() < 1e-5);
+    assert!(close(rec.normal, Vec3A::Y));
+    assert!(disk
+        .hit(&ray(Vec3A::new(0.25, 1.0, 0.0), -Vec3A::Y), 0.0, 10.0)
+        .is_none());
+}
+
+fn keyframes(values: &[(f32, f32)]) -> Vec<Keyframe<f32>> {
+    values
+        .iter()
+        .map(|&(time, value)| Keyframe { time, value })
+        .collect()
+}
+
+#[test]
+fn linear_track_interpolates_and_holds_ends() {
+    // Keyframes are sorted by time
+    let track = Track::new(
+        keyframes(&[(3.0, 6.0), (0.0, 0.0), (1.0, 2.0)]),
+        Interpolation::Linear,
+    );
+
+    assert_eq!(track.sample(0.5), 1.0);
+    assert_eq!(track.sample(2.0), 4.0);
+    assert_eq!(track.sample(1.0), 2.0);
+    assert_eq!(track.sample(-1.0), 0.0);
+    assert_eq!(track.sample(5.0), 6.0);
+}
+
+#[test]
+fn cubic_track_passes_through_keyframes() {
+    let values = [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 1.0)];
+    let track = Track::new(keyframes(&values), Interpolation::Cubic);
+
+    for (time, value) in values {
+        assert!((track.sample(time) - value).abs() < 1e-6);
+    }
+
+    // Catmull-Rom between the middle keyframes, and with the end repeated in the first segment
+    assert!((track.sample(1.5) - 0.5).abs() < 1e-6);
+    assert!((track.sample(0.5) - 0.5625).abs() < 1e-6);
+
+    assert_eq!(track.sample(-1.0), 0.0);
+    assert_eq!(track.sample(10.0), 1.0);
+}
+
+#[test]
+fn camera_animation_samples_its_tracks() {
+    let animation = CameraAnimation {
+        lookfrom: Track::new(
+            vec![
+                Keyframe {
+                    time: 0.0,
+                    value: Vec3A::new(0.0, 0.0, 10.0),
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: Vec3A::new(10.0, 0.0, 10.0),
+                },
+            ],
+            Interpolation::Linear,
+        ),
+        lookat: Track::constant(Vec3A::ZERO),
+        vup: Vec3A::Y,
+        vfov: Track::constant(20.0),
+        aspect_ratio: 1.5,
+        aperture: Track::constant(0.0),
+        focus_dist: Track::constant(10.0),
+    };
+
+    let camera = animation.camera_at(0.5);
+    let expected = Camera::new(
+        Vec3A::new(5.0, 0.0, 10.0),
+        Vec3A::ZERO,
+        Vec3A::Y,
+        20.0,
+        1.5,
+        0.0,
+        10.0,
+    );
+    assert!((camera.origin - expected.origin).length() < 1e-6);
+    assert!((camera.lower_left_corner - expected.lower_left_corner).length() < 1e-5);
+}
+
+#[test]
+fn animation_frame_moves_objects_over_the_shutter() {
+    let ball = AnimatedObject {
+        object: Arc::new(sphere(Vec3A::ZERO, 0.1)),
+        scale: Track::constant(Vec3::ONE),
+        rotation: Track::constant(Quat::IDENTITY),
+        translation: Track::new(
+            vec![
+                Keyframe {
+                    time: 0.0,
+                    value: Vec3::ZERO,
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: Vec3::new(24.0, 0.0, 0.0),
+                },
+            ],
+            Interpolation::Linear,
+        ),
+    };
+    let animation = Animation {
+        camera: CameraAnimation {
+            lookfrom: Track::constant(Vec3A::new(0.0, 0.0, 10.0)),
+            lookat: Track::constant(Vec3A::ZERO),
+            vup: Vec3A::Y,
+            vfov: Track::constant(20.0),
+            aspect_ratio: 1.5,
+            aperture: Track::constant(0.0),
+            focus_dist: Track::constant(10.0),
+        },
+        background: Arc::new(sphere(Vec3A::new(0.0, -100.0, 0.0), 1.0)),
+        unbounded: vec![],
+        objects: vec![ball],
+        frames_per_second: 24.0,
+        shutter: 0.5,
+    };
+
+    // Frame 12 starts half a second in, with the shutter open for half a frame
+    let (camera, objects) = animation.frame(12);
+    assert_eq!(camera.time0, 0.5);
+    assert!((camera.time1 - (0.5 + 0.5 / 24.0)).abs() < 1e-6);
+    assert_eq!(objects.len(), 2);
+
+    let ball = &objects[1];
+    for (time, x) in [(camera.time0, 12.0), (camera.time1, 12.5)] {
+        let mut r = ray(Vec3A::new(x, 0.0, 5.0), Vec3A::new(0.0, 0.0, -1.0));
+        r.time = time;
+        assert!(ball.hit(&r, 0.001, f32::MAX).is_some());
+
+        // Where the ball is at the other end of the shutter
+        r.time = camera.time0 + camera.time1 - time;
+        assert!(ball.hit(&r, 0.001, f32::MAX).is_none());
+    }
+}
+
+#[test]
+fn quad_hit_with_uv_and_flat_bounds() {
+    let quad = Quad::new(
+        Vec3A::ZERO,
+        Vec3A::new(2.0, 0.0, 0.0),
+        Vec3A::new(0.0, 1.0, 0.0),
+        white(),
+    );
+
+    let r = ray(Vec3A::new(0.5, 0.25, 3.0), Vec3A::new(0.0, 0.0, -1.0));
+    let rec = quad.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 3.0).abs() < 1e-6);
+    assert!((Vec2::new(rec.u, rec.v) - Vec2::new(0.25, 0.25)).length() < 1e-6);
+    assert!((rec.normal - Vec3A::Z).length() < 1e-6);
+    assert!(rec.front_face);
+
+    let back = ray(Vec3A::new(0.5, 0.25, -3.0), Vec3A::new(0.0, 0.0, 1.0));
+    let rec = quad.hit(&back, 0.001, f32::MAX).unwrap();
+    assert!((rec.normal + Vec3A::Z).length() < 1e-6);
+    assert!(!rec.front_face);
+
+    let outside = ray(Vec3A::new(2.5, 0.5, 3.0), Vec3A::new(0.0, 0.0, -1.0));
+    assert!(quad.hit(&outside, 0.001, f32::MAX).is_none());
+    let parallel = ray(Vec3A::new(-1.0, 0.5, 0.0), Vec3A::X);
+    assert!(quad.hit(&parallel, 0.001, f32::MAX).is_none());
+
+    // The box has no depth, but rays through it still enter it
+    let aabb = quad.bounding_box();
+    assert_eq!(aabb.minimum.z, aabb.maximum.z);
+    assert_eq!(r.aabb_interval(aabb), Some((3.0, 3.0)));
+
+    let objects: Vec<Box<dyn Hittable>> = vec![Box::new(quad)];
+    let bvh = BVH::build(&objects);
+    assert!(r.hit(bvh.traverse(&r, &objects)).is_some());
+}
+
+#[test]
+fn axis_rect_hit_with_uv() {
+    // In the zx plane at y = 1, with u running along z and v along x
+    let rect = AxisRect {
+        axis: Axis::Y,
+        a0: 0.0,
+        a1: 2.0,
+        b0: 0.0,
+        b1: 1.0,
+        k: 1.0,
+        material: white(),
+    };
+
+    let r = ray(Vec3A::new(0.5, 5.0, 1.5), Vec3A::new(0.0, -1.0, 0.0));
+    let rec = rect.hit(&r, 0.001, f32::MAX).unwrap();
+    assert!((rec.t - 4.0).abs() < 1e-6);
+    assert!((Vec2::new(rec.u, rec.v) - Vec2::new(0.75, 0.5)).length() < 1e-6);
+    assert!((rec.normal - Vec3A::Y).length() < 1e-6);
+    assert!(rec.front_face);
+
+    let outside = ray(Vec3A::new(1.5, 5.0, 1.5), Vec3A::new(0.0, -1.0, 0.0));
+    assert!(rect.hit(&outside, 0.001, f32::MAX).is_none());
+
+    let aabb = rect.bounding_box();
+    assert_eq!(aabb.minimum, Vec3A::new(0.0, 1.0, 0.0));
+    assert_eq!(aabb.maximum, Vec3A::new(1.0, 1.0, 2.0));
+    assert!(r.aabb_interval(aabb).is_some());
+}
+
+#[test]
+fn cuboid_faces_point_outwards() {
+    let cuboid = Cuboid::new(Vec3A::ZERO, Vec3A::new(1.0, 2.0, 3.0), white());
+
+    // (origin, direction, distance, outward normal, entering)
+    let cases = [
+        (Vec3A::new(-1.0, 1.0, 1.0), Vec3A::X, 1.0, -Vec3A::X, true),
+        (Vec3A::new(2.0, 1.0, 1.0), -Vec3A::X, 1.0, Vec3A::X, true),
+        (Vec3A::new(0.5, 1.0, 1.0), Vec3A::X, 0.5, Vec3A::X, false),
+        (Vec3A::new(0.5, 1.0, 1.0), -Vec3A::X, 0.5, -Vec3A::X, false),
+        (Vec3A::new(0.5, 1.0, 5.0), -Vec3A::Z, 2.0, Vec3A::Z, true),
+        (Vec3A::new(0.5, 1.0, 1.0), -Vec3A::Y, 1.0, -Vec3A::Y, false),
+    ];
+    for (origin, direction, distance, outward, entering) in cases {
+        let rec = cuboid
+            .hit(&ray(origin, direction), 0.001, f32::MAX)
+            .unwrap();
+        assert!((rec.t - distance).abs() < 1e-6);
+        assert_eq!(rec.front_face, entering);
+
+        // The normal faces the ray, so it's the outward one when entering
+        let facing = if entering { outward } else { -outward };
+        assert!((rec.normal - facing).length() < 1e-6);
+    }
+
+    // Sides get their own texture coordinates, the x sides run along y and then z
+    let rec = cuboid
+        .hit(&ray(Vec3A::new(-1.0, 1.0, 1.0), Vec3A::X), 0.001, f32::MAX)
+        .unwrap();
+    assert!((Vec2::new(rec.u, rec.v) - Vec2::new(0.5, 1.0 / 3.0)).length() < 1e-6);
+}
+
+#[test]
+#[should_panic(expected = "positive radius and height")]
+fn cylinder_rejects_zero_radius() {
+    Cylinder::new(Vec3A::ZERO, 0.0, 1.0, true, white());
+}
+
+#[test]
+#[should_panic(expected = "positive radius and height")]
+fn cone_rejects_negative_height() {
+    Cone::new(Vec3A::ZERO, 1.0, -1.0, true, white());
+}
+
+#[test]
+#[should_panic(expected = "positive radius and height")]
+fn paraboloid_rejects_zero_height() {
+    Paraboloid::new(Vec3A::ZERO, 1.0, 0.0, false, white());
+}
+
+#[test]
+fn plane_hit_miss_and_parallel() {
+    let plane = Plane::new(
+        Vec3A::new(0.0, 1.0, 0.0),
+        Vec3A::new(0.0, 2.0, 0.0),
+        white(),
+    );
+
+    let rec = plane
+        .hit(
+            &ray(Vec3A::new(3.25, 5.0, -7.5), Vec3A::new(0.0, -2.0, 0.0)),
+            0.001,
+            f32::MAX,
+        )
+        .unwrap();
+    assert!((rec.t - 2.0).abs() < 1e-6);
+    assert!((rec.p - Vec3A::new(3.25, 1.0, -7.5)).length() < 1e-6);
+    assert!((rec.normal - Vec3A::Y).length() < 1e-6);
+    assert!(rec.front_face);
+    assert!((0.0..1.0).contains(&rec.u) && (0.0..1.0).contains(&rec.v));
+
+    // Seen from below the normal flips
+    let rec = plane
+        .hit(&ray(Vec3A::ZERO, Vec3A::Y), 0.001, f32::MAX)
+        .unwrap();
+    assert!((rec.normal + Vec3A::Y).length() < 1e-6);
+    assert!(!rec.front_face);
+
+    let away = ray(Vec3A::new(0.0, 5.0, 0.0), Vec3A::Y);
+    assert!(plane.hit(&away, 0.001, f32::MAX).is_none());
+    let parallel = ray(Vec3A::new(0.0, 5.0, 0.0), Vec3A::X);
+    assert!(plane.hit(&parallel, 0.001, f32::MAX).is_none());
+}
+
+#[test]
+fn scene_keeps_planes_out_of_the_bvh() {
+    let mut scene = Scene::new();
+    scene.set(vec![
+        Box::new(Plane::new(Vec3A::ZERO, Vec3A::Y, white())),
+        Box::new(sphere(Vec3A::new(0.0, 1.0, 0.0), 1.0)),
+    ]);
+
+    assert_eq!(scene.objects.len(), 1);
+    assert_eq!(scene.unbounded.len(), 1);
+
+    let aabb = scene.objects[0].bounding_box();
+    assert!(aabb.minimum.is_finite() && aabb.maximum.is_finite());
+
+    let bvh = BVH::build(&scene.objects);
+
+    // Rays passing the sphere still find the plane
+    let r = ray(Vec3A::new(5.0, 5.0, 0.0), Vec3A::new(0.0, -1.0, 0.0));
+    let mut shapes = bvh.traverse(&r, &scene.objects);
+    shapes.extend(&scene.unbounded);
+    assert!((r.hit(shapes).unwrap().t - 5.0).abs() < 1e-6);
+}
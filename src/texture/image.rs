@@ -19,7 +19,8 @@ impl ImageTexture {
         Self {
             height: data.height() as usize,
             width: data.width() as usize,
-            data: data.as_bytes().to_vec(),
+            // Grayscale and RGBA images, like displacement maps, are stored as RGB too
+            data: data.to_rgb8().into_raw(),
         }
     }
 }
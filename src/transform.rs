@@ -0,0 +1,188 @@
+use glam::{Affine3A, Quat, Vec3, Vec3A};
+
+use crate::{
+    aabb::AABB,
+    hittable::Hittable,
+    ray::{HitRecord, Ray},
+    util::degrees_to_radians,
+};
+
+// The wrappers transform the ray into object space without normalizing its direction, so the
+// ray parameter `t` of a hit is the same in object and world space.
+
+/// Moves an object by `offset`.
+pub struct Translate<H: Hittable> {
+    pub object: H,
+    offset: Vec3A,
+    aabb: AABB,
+}
+
+impl<H: Hittable> Translate<H> {
+    pub fn new(object: H, offset: Vec3A) -> Self {
+        let aabb = object.bounding_box();
+
+        Self {
+            aabb: AABB::new(aabb.minimum + offset, aabb.maximum + offset),
+            object,
+            offset,
+        }
+    }
+}
+
+impl<H: Hittable> Translate<H> {
+    fn local_ray(&self, r: &Ray) -> Ray {
+        Ray {
+            origin: r.origin - self.offset,
+            direction: r.direction,
+            time: r.time,
+        }
+    }
+
+    fn to_world(&self, mut rec: HitRecord) -> HitRecord {
+        rec.p += self.offset;
+        rec
+    }
+}
+
+impl<H: Hittable> Hittable for Translate<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let rec = self.object.hit(&self.local_ray(r), t_min, t_max)?;
+
+        Some(self.to_world(rec))
+    }
+
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        self.object
+            .hit_all(&self.local_ray(r), t_min, t_max)
+            .into_iter()
+            .map(|rec| self.to_world(rec))
+            .collect()
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
+
+/// Rotates an object around `axis` through the origin.
+pub struct Rotate<H: Hittable> {
+    pub object: H,
+    rotation: Quat,
+    aabb: AABB,
+}
+
+impl<H: Hittable> Rotate<H> {
+    pub fn new(object: H, axis: Vec3A, degrees: f32) -> Self {
+        let rotation =
+            Quat::from_axis_angle(Vec3::from(axis).normalize(), degrees_to_radians(degrees));
+
+        Self {
+            aabb: object
+                .bounding_box()
+                .transform(&Affine3A::from_quat(rotation)),
+            object,
+            rotation,
+        }
+    }
+}
+
+impl<H: Hittable> Rotate<H> {
+    fn local_ray(&self, r: &Ray) -> Ray {
+        let inverse = self.rotation.inverse();
+
+        Ray {
+            origin: inverse * r.origin,
+            direction: inverse * r.direction,
+            time: r.time,
+        }
+    }
+
+    fn to_world(&self, mut rec: HitRecord) -> HitRecord {
+        rec.p = self.rotation * rec.p;
+        rec.normal = self.rotation * rec.normal;
+        rec
+    }
+}
+
+impl<H: Hittable> Hittable for Rotate<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let rec = self.object.hit(&self.local_ray(r), t_min, t_max)?;
+
+        Some(self.to_world(rec))
+    }
+
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        self.object
+            .hit_all(&self.local_ray(r), t_min, t_max)
+            .into_iter()
+            .map(|rec| self.to_world(rec))
+            .collect()
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
+
+/// Scales an object, possibly by a different factor along each axis, around the origin.
+pub struct Scale<H: Hittable> {
+    pub object: H,
+    scale: Vec3A,
+    aabb: AABB,
+}
+
+impl<H: Hittable> Scale<H> {
+    pub fn new(object: H, scale: Vec3A) -> Self {
+        assert!(
+            scale.cmpne(Vec3A::ZERO).all(),
+            "scale flattens the object along an axis"
+        );
+
+        let aabb = object.bounding_box();
+        let a = aabb.minimum * scale;
+        let b = aabb.maximum * scale;
+
+        Self {
+            aabb: AABB::new(a.min(b), a.max(b)),
+            object,
+            scale,
+        }
+    }
+}
+
+impl<H: Hittable> Scale<H> {
+    fn local_ray(&self, r: &Ray) -> Ray {
+        Ray {
+            origin: r.origin / self.scale,
+            direction: r.direction / self.scale,
+            time: r.time,
+        }
+    }
+
+    fn to_world(&self, mut rec: HitRecord) -> HitRecord {
+        rec.p *= self.scale;
+        // Normals transform with the inverse transpose, which for a scale is its reciprocal
+        rec.normal = (rec.normal / self.scale).normalize();
+        rec
+    }
+}
+
+impl<H: Hittable> Hittable for Scale<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let rec = self.object.hit(&self.local_ray(r), t_min, t_max)?;
+
+        Some(self.to_world(rec))
+    }
+
+    fn hit_all(&self, r: &Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        self.object
+            .hit_all(&self.local_ray(r), t_min, t_max)
+            .into_iter()
+            .map(|rec| self.to_world(rec))
+            .collect()
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.aabb
+    }
+}
@@ -6,11 +6,11 @@ pub fn degrees_to_radians(degrees: f32) -> f32 {
     degrees * PI / 180.0
 }
 
-pub fn joint_aabb(indices: &[usize], shapes: &[Box<dyn Hittable>]) -> AABB {
+pub fn joint_aabb(indices: &[usize], aabbs: &[AABB]) -> AABB {
     let mut aabb = AABB::empty();
 
     for index in indices {
-        aabb.join_mut(&shapes[*index].bounding_box());
+        aabb.join_mut(&aabbs[*index]);
     }
 
     aabb
@@ -35,3 +35,124 @@ pub fn concatenate_vectors<T: Sized>(vectors: &mut [Vec<T>]) -> Vec<T> {
 
     vec
 }
+
+/// Real roots of `a x^2 + b x + c`, in no particular order.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return if b == 0.0 { vec![] } else { vec![-c / b] };
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+
+    // Avoids the cancellation of the textbook formula
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return vec![0.0];
+    }
+
+    vec![q / a, c / q]
+}
+
+/// Real roots of the monic cubic `x^3 + a x^2 + b x + c`.
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // Substitute x = y - a/3 to eliminate the quadratic term: y^3 + 3p y + 2q
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+
+    let cb_p = p * p * p;
+    let discriminant = q * q + cb_p;
+
+    let roots = if discriminant.abs() < 1e-14 {
+        if q.abs() < 1e-14 {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if discriminant < 0.0 {
+        // Three real roots
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + std::f64::consts::PI / 3.0).cos(),
+            -t * (phi - std::f64::consts::PI / 3.0).cos(),
+        ]
+    } else {
+        let sqrt_d = discriminant.sqrt();
+        vec![(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt()]
+    };
+
+    roots.into_iter().map(|y| y - a / 3.0).collect()
+}
+
+/// Real roots of `c4 x^4 + c3 x^3 + c2 x^2 + c1 x + c0` using Ferrari's method, polished with a
+/// few Newton iterations since the closed form loses precision.
+pub fn solve_quartic(c4: f64, c3: f64, c2: f64, c1: f64, c0: f64) -> Vec<f64> {
+    if c4 == 0.0 {
+        return solve_cubic_general(c3, c2, c1, c0);
+    }
+
+    // Normal form x^4 + a x^3 + b x^2 + c x + d
+    let a = c3 / c4;
+    let b = c2 / c4;
+    let c = c1 / c4;
+    let d = c0 / c4;
+
+    // Substitute x = y - a/4 to eliminate the cubic term: y^4 + p y^2 + q y + r
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = 1.0 / 8.0 * sq_a * a - 1.0 / 2.0 * a * b + c;
+    let r = -3.0 / 256.0 * sq_a * sq_a + 1.0 / 16.0 * sq_a * b - 1.0 / 4.0 * a * c + d;
+
+    let roots = if r.abs() < 1e-14 {
+        // y (y^3 + p y + q) = 0
+        let mut roots = solve_cubic(0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // Solve the resolvent cubic and take one real root
+        let z = solve_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0)[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        if u < -1e-12 || v < -1e-12 {
+            vec![]
+        } else {
+            let u = u.max(0.0).sqrt();
+            let v = v.max(0.0).sqrt();
+            let v = if q < 0.0 { -v } else { v };
+
+            let mut roots = solve_quadratic(1.0, v, z - u);
+            roots.extend(solve_quadratic(1.0, -v, z + u));
+            roots
+        }
+    };
+
+    roots
+        .into_iter()
+        .map(|y| {
+            let mut x = y - a / 4.0;
+            for _ in 0..2 {
+                let f = (((x + a) * x + b) * x + c) * x + d;
+                let df = ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+                if df != 0.0 {
+                    x -= f / df;
+                }
+            }
+            x
+        })
+        .collect()
+}
+
+fn solve_cubic_general(c3: f64, c2: f64, c1: f64, c0: f64) -> Vec<f64> {
+    if c3 == 0.0 {
+        solve_quadratic(c2, c1, c0)
+    } else {
+        solve_cubic(c2 / c3, c1 / c3, c0 / c3)
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    sync::Arc,
+};
+
+use glam::{Vec2, Vec3A};
+use rand::Rng;
+
+use crate::{
+    aabb::AABB,
+    hittable::Hittable,
+    material::Material,
+    medium::HenyeyGreenstein,
+    perlin::Perlin,
+    ray::{HitRecord, Ray},
+    texture::color::SolidColor,
+};
+
+/// Dense grid of values, like densities or temperatures, stored with x varying fastest and
+/// sampled at the voxel centers.
+#[derive(Clone, Debug)]
+pub struct VoxelGrid {
+    pub dims: [usize; 3],
+    pub data: Vec<f32>,
+}
+
+impl VoxelGrid {
+    pub fn new(dims: [usize; 3], data: Vec<f32>) -> Self {
+        assert_eq!(
+            data.len(),
+            dims[0] * dims[1] * dims[2],
+            "voxels don't match the dimensions"
+        );
+
+        Self { dims, data }
+    }
+
+    /// Loads a grid from a binary file of the three dimensions as little endian `u32`s followed
+    /// by the values as little endian `f32`s, x varying fastest.
+    pub fn load(path: &str) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |message: String| Error::new(ErrorKind::InvalidData, message);
+
+        if bytes.len() < 12 {
+            return Err(invalid(format!("{}: missing grid dimensions", path)));
+        }
+
+        let word = |i: usize| {
+            [
+                bytes[4 * i],
+                bytes[4 * i + 1],
+                bytes[4 * i + 2],
+                bytes[4 * i + 3],
+            ]
+        };
+        let dims = [0, 1, 2].map(|i| u32::from_le_bytes(word(i)) as usize);
+
+        let count = dims[0] * dims[1] * dims[2];
+        if bytes.len() != 12 + 4 * count {
+            return Err(invalid(format!(
+                "{}: expected {} voxels for {:?}, found {} bytes",
+                path,
+                count,
+                dims,
+                bytes.len() - 12
+            )));
+        }
+
+        let data = (0..count)
+            .map(|i| f32::from_le_bytes(word(3 + i)))
+            .collect();
+
+        Ok(Self { dims, data })
+    }
+
+    /// Generates a cloud from `octaves` of the Perlin noise, made to fade out towards the
+    /// sides of the grid. The finest octave changes every voxel, every next one half as often.
+    pub fn from_noise(dims: [usize; 3], octaves: u32) -> Self {
+        let perlin = Perlin::new();
+
+        // Noise interpolated smoothly between its values on a lattice
+        let smooth_noise = |p: Vec3A| {
+            let cell = p.floor();
+            let f = p - cell;
+            let f = f * f * (Vec3A::splat(3.0) - 2.0 * f);
+
+            let mut value = 0.0;
+            for corner in 0..8 {
+                let offset = Vec3A::new(
+                    (corner & 1) as f32,
+                    (corner >> 1 & 1) as f32,
+                    (corner >> 2 & 1) as f32,
+                );
+                let weight = Vec3A::ONE - offset + (2.0 * offset - Vec3A::ONE) * f;
+
+                // The noise is constant over cells a quarter wide, so sample it at their middles
+                let lattice = (cell + offset) / 4.0 + Vec3A::splat(0.125);
+                value += weight.x * weight.y * weight.z * perlin.noise(lattice);
+            }
+
+            value
+        };
+
+        let size = Vec3A::new(dims[0] as f32, dims[1] as f32, dims[2] as f32);
+
+        let mut data = Vec::with_capacity(dims[0] * dims[1] * dims[2]);
+        for z in 0..dims[2] {
+            for y in 0..dims[1] {
+                for x in 0..dims[0] {
+                    let p = Vec3A::new(x as f32, y as f32, z as f32) + Vec3A::splat(0.5);
+
+                    // Coarser octaves weigh more, offset so they don't line up
+                    let mut noise = 0.0;
+                    let mut total = 0.0;
+                    for octave in 0..octaves {
+                        let scale = (1 << octave) as f32;
+                        noise +=
+                            scale * smooth_noise(p / scale + Vec3A::splat(17.0 * octave as f32));
+                        total += scale;
+                    }
+
+                    // Distance from the center, 1 at the sides
+                    let falloff = ((2.0 * p / size - Vec3A::ONE).length()).min(1.0);
+
+                    data.push((noise / total - falloff * falloff).max(0.0));
+                }
+            }
+        }
+
+        Self::new(dims, data)
+    }
+
+    pub fn max(&self) -> f32 {
+        self.data.iter().copied().fold(0.0, f32::max)
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.data[(z * self.dims[1] + y) * self.dims[0] + x]
+    }
+
+    /// Trilinearly interpolated value at `p`, from 0 to 1 over the grid along every axis.
+    pub fn lookup(&self, p: Vec3A) -> f32 {
+        let dims = Vec3A::new(
+            self.dims[0] as f32,
+            self.dims[1] as f32,
+            self.dims[2] as f32,
+        );
+        let p = (p * dims - Vec3A::splat(0.5)).clamp(Vec3A::ZERO, dims - Vec3A::ONE);
+
+        let cell = p.floor();
+        let f = p - cell;
+        let [x0, y0, z0] = [cell.x as usize, cell.y as usize, cell.z as usize];
+        let [x1, y1, z1] = [
+            (x0 + 1).min(self.dims[0] - 1),
+            (y0 + 1).min(self.dims[1] - 1),
+            (z0 + 1).min(self.dims[2] - 1),
+        ];
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let front = lerp(
+            lerp(self.voxel(x0, y0, z0), self.voxel(x1, y0, z0), f.x),
+            lerp(self.voxel(x0, y1, z0), self.voxel(x1, y1, z0), f.x),
+            f.y,
+        );
+        let back = lerp(
+            lerp(self.voxel(x0, y0, z1), self.voxel(x1, y0, z1), f.x),
+            lerp(self.voxel(x0, y1, z1), self.voxel(x1, y1, z1), f.x),
+            f.y,
+        );
+
+        lerp(front, back, f.z)
+    }
+}
+
+/// Phase function of a heterogeneous medium, which also emits the light of the emission grid
+/// where the medium collides.
+#[derive(Debug)]
+struct VolumePhase {
+    phase: HenyeyGreenstein,
+    emission: Option<(VoxelGrid, Vec3A)>,
+    bounds: AABB,
+}
+
+impl Material for VolumePhase {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> std::result::Result<(Vec3A, Ray), ()> {
+        self.phase.scatter(ray, rec)
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, p: Vec3A) -> Vec3A {
+        match &self.emission {
+            Some((grid, color)) => {
+                let local = (p - self.bounds.minimum) / self.bounds.size();
+                grid.lookup(local) * *color
+            }
+            None => Vec3A::ZERO,
+        }
+    }
+}
+
+/// Medium whose density varies over a voxel grid stretched over `bounds`, like clouds, smoke
+/// or fire. Collisions are found by delta tracking against the highest density in the grid,
+/// so the medium is sampled exactly however it varies.
+///
+/// There is no ratio tracking: the integrator traces no shadow rays, so nothing asks for the
+/// transmittance along a ray, and light through the medium is only estimated by rays that
+/// either pass it or collide. Like a `ConstantMedium`, it samples every `hit` anew and can't be
+/// a CSG operand.
+pub struct HeterogeneousMedium {
+    density: VoxelGrid,
+    /// Extinction coefficient for a density of 1.
+    scale: f32,
+    majorant: f32,
+    bounds: AABB,
+    phase: Arc<VolumePhase>,
+}
+
+impl HeterogeneousMedium {
+    /// Creates a medium scattering light of the `albedo` by the Henyey-Greenstein phase function
+    /// with asymmetry `g`. With an `emission` grid and color, the medium also emits the color
+    /// times the grid values where it collides, like fire with a temperature grid.
+    pub fn new(
+        density: VoxelGrid,
+        scale: f32,
+        bounds: AABB,
+        albedo: Vec3A,
+        g: f32,
+        emission: Option<(VoxelGrid, Vec3A)>,
+    ) -> Self {
+        Self {
+            majorant: density.max() * scale,
+            density,
+            scale,
+            bounds,
+            phase: Arc::new(VolumePhase {
+                phase: HenyeyGreenstein {
+                    albedo: Box::new(SolidColor::new(albedo.x, albedo.y, albedo.z)),
+                    g,
+                },
+                emission,
+                bounds,
+            }),
+        }
+    }
+
+    fn extinction(&self, p: Vec3A) -> f32 {
+        let local = (p - self.bounds.minimum) / self.bounds.size();
+
+        self.scale * self.density.lookup(local)
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (enter, exit) = r.aabb_interval(self.bounds)?;
+        let (mut t, exit) = (enter.max(t_min), exit.min(t_max));
+        if self.majorant <= 0.0 || t >= exit {
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+        let step = 1.0 / (self.majorant * r.direction.length());
+
+        // Step to tentative collisions with the majorant, taking those that are real
+        loop {
+            t -= (1.0 - rng.gen::<f32>()).ln() * step;
+            if t >= exit {
+                return None;
+            }
+
+            let p = r.at(t);
+            if rng.gen::<f32>() * self.majorant < self.extinction(p) {
+                return Some(HitRecord {
+                    p,
+                    normal: Vec3A::X,
+                    t,
+                    u: 0.0,
+                    v: 0.0,
+                    barycentric: Vec2::ZERO,
+                    front_face: true,
+                    material: self.phase.clone(),
+                    media: None,
+                });
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bounds
+    }
+}